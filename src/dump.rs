@@ -4,20 +4,23 @@
 use std::{
 	fs,
 	io::Write,
+	path::Path,
 };
 use crate::{
 	file_structure,
 	lumps::{
 		vbsp::{self, VBSPLumpType},
-		goldsrc::{self, GoldSrcLumpType},
-		quake::{self, QuakeLumpType},
+		goldsrc::GoldSrcLumpType,
+		quake::QuakeLumpType,
 		lumptype::Lumps,
 	},
 	specific::{
 		occlusion,
 		physcol_data::ModelHeaders,
 		gamelump,
+		vis::BitSet,
 	},
+	tools::{ambient, areas, content, q1bsp::Q1Bsp, vbsp::VBsp, wad},
 	VERSION,
 	utils::{
 		bitflags_to_string,
@@ -29,6 +32,7 @@ use crate::{
 pub fn dump(
 	path: String,
 	file: file_structure::BSPFile,
+	game_dir: Option<String>, // where to look for wads, goldsrc and quake only
 ) {
 	match file.header.bspver {
 		file_structure::BSPVersion::VBSP => dump_vbsp(
@@ -40,11 +44,13 @@ pub fn dump(
 			path,
 			file.header,
 			if let Lumps::GoldSrc(ld) = file.lump_data { ld } else { panic!("huh") },
+			game_dir,
 		),
 		file_structure::BSPVersion::Quake => dump_quake(
 			path,
 			file.header,
-			if let Lumps::Quake(ld) = file.lump_data { ld } else { panic!("huh") }
+			if let Lumps::Quake(ld) = file.lump_data { ld } else { panic!("huh") },
+			game_dir,
 		),
 		file_structure::BSPVersion::None => panic!("should have version by now lmao"),
 	}
@@ -74,6 +80,17 @@ pub fn dump_vbsp(
 		header.map_revision,
 	));
 
+	let bsp: VBsp = VBsp::from_lumps(&ld);
+	to_write.push_str(&format!(
+		"lighting: {}\n\n",
+		match (bsp.lighting.is_empty(), bsp.lighting_hdr.is_empty()) {
+			(false, false) => "ldr and hdr",
+			(false, true) => "ldr only",
+			(true, false) => "hdr only",
+			(true, true) => "none (fullbright)",
+		},
+	));
+
 	for l_info in header.lumps {
 		to_write.push_str(&format!(
 			"lump {} info:\n\tfile offset: {} bytes\n\t",
@@ -171,27 +188,32 @@ pub fn dump_vbsp(
 			));
 			counter += 1;
 		}
-		to_write.push_str("\tdata:\n\t\tWARNING this might be incorrect! i haven't found a way to check :(\n");
+		to_write.push_str("\tdata:\n");
 		for cl in 0..vis.num_clusters {
-			to_write.push_str(&format!("\t\t[cluster{cl}]\n\t\t\tvisible clusters:\n\t\t\t\t"));
+			let (pvs, pas): (BitSet, BitSet) = (vis.pvs(cl), vis.pas(cl));
+			to_write.push_str(&format!(
+				"\t\t[cluster{cl}] {} visible, {} audible\n",
+				pvs.count_ones(), pas.count_ones(),
+			));
+			// a cluster always sees itself, if it doesnt the offsets or the data are broken
+			if !pvs.get(cl as usize) || !pas.get(cl as usize) {
+				to_write.push_str("\t\t\twarning: cluster can't see or hear itself\n");
+			}
+			to_write.push_str("\t\t\tvisible clusters:\n\t\t\t\t");
 			let mut cl_str: String = "".to_string();
 			let mut c = 0;
-			for i in 0..vis.cluster_data[0][cl as usize].len() {
+			for i in pvs.ones() {
 				if c > 25 { cl_str.push_str("\n\t\t\t\t"); c = 0 }
-				if vis.cluster_data[0][cl as usize][i] {
-					cl_str.push_str(&format!("{}, ", i)); c += 1;
-				}
+				cl_str.push_str(&format!("{}, ", i)); c += 1;
 			}
 			to_write.push_str(&cl_str);
 
-			to_write.push_str(&format!("\n\t\t\taudible clusters:\n\t\t\t\t"));
+			to_write.push_str("\n\t\t\taudible clusters:\n\t\t\t\t");
 			cl_str = "".to_string();
 			c = 0;
-			for i in 0..vis.cluster_data[1][cl as usize].len() {
+			for i in pas.ones() {
 				if c > 25 { cl_str.push_str("\n\t\t\t\t"); c = 0 }
-				if vis.cluster_data[1][cl as usize][i] {
-					cl_str.push_str(&format!("{}, ", i)); c += 1;
-				}
+				cl_str.push_str(&format!("{}, ", i)); c += 1;
 			}
 			to_write.push_str(&cl_str);
 			to_write.push('\n');
@@ -271,6 +293,7 @@ pub fn dump_vbsp(
 	// LUMP_FACES
 	to_write.push_str("\nLUMP_FACES (index 7)\n");
 	if let VBSPLumpType::Faces(faces) = &ld[7] {
+		let extent_problems: Vec<(usize, String)> = bsp.lightmap_extent_problems();
 		let mut counter: u32 = 0;
 		// this is a big one
 		for face in faces {
@@ -300,6 +323,16 @@ pub fn dump_vbsp(
 				"\t\torig_face: {}\n\t\tfirst_prim_id, num_prims: {}, {}\n\t\tsmoothing_groups: {}\n",
 				face.orig_face, face.first_prim_id, face.num_prims, face.smoothing_groups,
 			));
+			to_write.push_str("\t\tvertices:\n");
+			for v in bsp.face_coords(face) {
+				to_write.push_str(&format!(
+					"\t\t\t{} uv: ({}, {}) luxel: ({}, {})\n",
+					v.pos, v.uv[0], v.uv[1], v.luxel[0], v.luxel[1],
+				));
+			}
+			for (_, problem) in extent_problems.iter().filter(|(i, _)| *i == counter as usize) {
+				to_write.push_str(&format!("\t\twarning: {problem}\n"));
+			}
 
 			counter += 1;
 		}
@@ -358,7 +391,7 @@ pub fn dump_vbsp(
 			to_write.push_str(&format!(
 				"\t\tcontents: {}\n\t\tcluster: {}\n\t\tarea: {}\n\t\tflags: {}\n",
 				bitflags_to_string(leaf.contents.iter_names()), leaf.cluster,
-				leaf.area_flags & (1 << 9 - 1), leaf.area_flags >> 9,
+				leaf.area_flags & ((1 << 9) - 1), leaf.area_flags >> 9,
 			));
 			to_write.push_str(&format!(
 				"\t\tmins: {:?}\n\t\tmaxs: {:?}\n\t\tfirst_leaf_face, num_leaf_faces: {}, {}\n",
@@ -456,12 +489,24 @@ pub fn dump_vbsp(
 	to_write.push_str("no information available yet!\n");
 
 	// LUMP_LEAFFACES
-	to_write.push_str("\nLUMP_WORLDLIGHTS (index 16)\n");
-	to_write.push_str("no information available yet!\n");
+	to_write.push_str("\nLUMP_LEAFFACES (index 16)\n");
+	if let VBSPLumpType::LeafFaces(leaffaces) = &ld[16] {
+		for (counter, face) in leaffaces.iter().enumerate() {
+			to_write.push_str(&format!("\t[leafface{counter}] face{face}\n"));
+		}
+
+		if leaffaces.is_empty() { to_write.push_str("\tlump is empty\n"); }
+	}
 
 	// LUMP_LEAFBRUSHES
 	to_write.push_str("\nLUMP_LEAFBRUSHES (index 17)\n");
-	to_write.push_str("no information available yet!\n");
+	if let VBSPLumpType::LeafBrushes(leafbrushes) = &ld[17] {
+		for (counter, brush) in leafbrushes.iter().enumerate() {
+			to_write.push_str(&format!("\t[leafbrush{counter}] brush{brush}\n"));
+		}
+
+		if leafbrushes.is_empty() { to_write.push_str("\tlump is empty\n"); }
+	}
 
 	// LUMP_BRUSHES
 	to_write.push_str("\nLUMP_BRUSHES (index 18)\n");
@@ -531,6 +576,9 @@ pub fn dump_vbsp(
 
 		if counter == 0 { to_write.push_str("\tlump is empty\n"); }
 	}
+	for problem in areas::build(&bsp).problems {
+		to_write.push_str(&format!("\twarning: {problem}\n"));
+	}
 
 	// LUMP_UNUSED22/23/24/25
 	to_write.push_str("\nLUMP_UNUSED22/23/24/25\n");
@@ -564,9 +612,8 @@ pub fn dump_vbsp(
 			let mut neighbor_counter: u8 = 0;
 			for neighbor in dispinfo.edge_neighbors {
 				to_write.push_str(&format!("\t\t\t[{neighbor_counter}]\n"));
-				to_write.push_str(&format!("\t\t\t\tsub_neighbors:\n"));
-				let mut subneighbor_counter: u8 = 0;
-				for sub_neighbor in neighbor.sub_neighbors {
+				to_write.push_str("\t\t\t\tsub_neighbors:\n");
+				for (subneighbor_counter, sub_neighbor) in neighbor.sub_neighbors.iter().enumerate() {
 					to_write.push_str(&format!("\t\t\t\t\t[{subneighbor_counter}]\n"));
 					to_write.push_str(&format!(
 						"\t\t\t\t\t\tneighbor: {}\n\t\t\t\t\t\torientation: {}\n\t\t\t\t\t\tspan: {}\n",
@@ -577,7 +624,6 @@ pub fn dump_vbsp(
 						"\t\t\t\t\t\tneighbor_span: {}\n\t\t\t\t\t\tpadding: {}\n",
 						sub_neighbor.neighbor_span, sub_neighbor.padding,
 					));
-					subneighbor_counter += 1;
 				}
 				neighbor_counter += 1;
 			}
@@ -666,8 +712,7 @@ pub fn dump_vbsp(
 				model.model_index, model.data_size, model.keydata_size, model.solid_count,
 			));
 			to_write.push_str("\t\tsolids:\n");
-			let mut data_counter: u32 = 0;
-			for data in &model.collision_data {
+			for (data_counter, data) in model.collision_data.iter().enumerate() {
 				to_write.push_str(&format!("\t\t\t[{data_counter}]\n"));
 				to_write.push_str(&format!(
 					"\t\t\t\tcollide_header:\n\t\t\t\t\tsize: {}\n\t\t\t\t\tid: {}\n\t\t\t\t\tversion: {}\n",
@@ -698,16 +743,49 @@ pub fn dump_vbsp(
 						m.size,
 					));
 				}
-				to_write.push_str(&format!("\t\t\t\tdata: {} bytes (format unknown)\n", data.data.len()));
-				data_counter += 1;
+				if data.ledges.is_empty() {
+					to_write.push_str(&format!("\t\t\t\tdata: {} bytes (format unknown)\n", data.data.len()));
+				} else {
+					to_write.push_str(&format!(
+						"\t\t\t\tdata: {} bytes\n\t\t\t\tconvexes: {}\n\t\t\t\ttriangles: {}\n",
+						data.data.len(),
+						data.ledges.len(),
+						data.ledges.iter().map(|l| l.triangles.len()).sum::<usize>(),
+					));
+				}
 			}
 			to_write.push_str("\t\tkey_data:\n");
-			for obj in &model.key_data {
-				to_write.push_str(&format!("\t\t\t{}\n", obj.0));
-				for attr in &obj.1 {
-					to_write.push_str(&format!("\t\t\t\t{}: {}\n", attr.0, attr.1));
+			for solid in &model.key_data.solids {
+				to_write.push_str(&format!(
+					"\t\t\tsolid\n\t\t\t\tindex: {}\n\t\t\t\tmass: {}\n\t\t\t\tsurfaceprop: {}\n\t\t\t\tdamping: {}\n\t\t\t\trotdamping: {}\n\t\t\t\tinertia: {}\n\t\t\t\tvolume: {}\n",
+					solid.index, solid.mass, solid.surfaceprop, solid.damping,
+					solid.rotdamping, solid.inertia, solid.volume,
+				));
+				for (key, value) in &solid.other {
+					to_write.push_str(&format!("\t\t\t\t{key}: {value}\n"));
+				}
+				if let Some(data) = usize::try_from(solid.index).ok().and_then(|i| model.collision_data.get(i)) {
+					to_write.push_str(&format!(
+						"\t\t\t\tsurfaceprops used: {}\n",
+						model.key_data.solid_surfaceprops(solid, data).join(", "),
+					));
+				}
+			}
+			if !model.key_data.material_table.is_empty() {
+				to_write.push_str("\t\t\tmaterialtable\n");
+				for (index, surfaceprop) in &model.key_data.material_table {
+					to_write.push_str(&format!("\t\t\t\t{index}: {surfaceprop}\n"));
+				}
+			}
+			for (name, pairs) in &model.key_data.other {
+				to_write.push_str(&format!("\t\t\t{name}\n"));
+				for (key, value) in pairs {
+					to_write.push_str(&format!("\t\t\t\t{key}: {value}\n"));
 				}
 			}
+			for problem in model.key_data.problems(&model.collision_data) {
+				to_write.push_str(&format!("\t\twarning: {problem}\n"));
+			}
 			counter += 1;
 		}
 
@@ -822,6 +900,13 @@ pub fn dump_vbsp(
 					sprp.leafs.leafs[c as usize],
 				));
 			}
+			to_write.push_str(&format!("\t\t\tprops:\n\t\t\t\tnum_entries: {}\n\t\t\t\tmodels:\n", sprp.num_entries));
+			for (c, prop_type) in sprp.prop_types.iter().enumerate() {
+				to_write.push_str(&format!(
+					"\t\t\t\t\t[{c}] {prop_type} ({})\n",
+					sprp.dict.names.get(*prop_type as usize).map_or("out of range", |n| n.as_str()),
+				));
+			}
 
 		}
 		to_write.push_str("\tno more data available\n");
@@ -889,6 +974,15 @@ pub fn dump_vbsp(
 			"\nLUMP_PAKFILE\n\t{} bytes read, dumped to zip file\n",
 			pakfile.bytes.len(),
 		));
+		let pak: content::Pak = content::Pak::new(&pakfile.bytes);
+		to_write.push_str(&format!("\t{} files:\n", pak.entries.len()));
+		for entry in &pak.entries {
+			to_write.push_str(&format!(
+				"\t\t{} ({} bytes{})\n",
+				entry.name, entry.size,
+				if entry.method != 0 { format!(", compressed with method {} to {}", entry.method, entry.compressed_size) } else { "".to_string() },
+			));
+		}
 	}
 
 	// LUMP_CLIPPORTALVERTS
@@ -958,7 +1052,7 @@ pub fn dump_vbsp(
 				"\t[overlay{counter}]\n\t\tid: {}\n\t\ttexinfo: {}\n\t\tface_count_and_render_order: {}\n",
 				overlay.id, overlay.texinfo, overlay.face_count_and_render_order,
 			));
-			to_write.push_str(&format!("\t\tfaces: "));
+			to_write.push_str("\t\tfaces: ");
 			for i in 0..64 {
 				if i % 8 == 0 && i != 0 { to_write.push_str("\n\t\t\t"); }
 				to_write.push_str(&format!("{}, ", overlay.faces[i]));
@@ -1017,6 +1111,96 @@ pub fn dump_vbsp(
 		&header.lumps[48].length
 	));
 
+	// LUMP_LEAF_AMBIENT_INDEX_HDR and LUMP_LEAF_AMBIENT_INDEX
+	for (lump_index, name, indices) in [
+		(51, "LUMP_LEAF_AMBIENT_INDEX_HDR", bsp.ambient_index_hdr),
+		(52, "LUMP_LEAF_AMBIENT_INDEX", bsp.ambient_index),
+	] {
+		to_write.push_str(&format!("\n{name} (index {lump_index})\n"));
+		for (counter, index) in indices.iter().enumerate() {
+			to_write.push_str(&format!(
+				"\t[leaf{counter}] ambient_sample_count: {}, first_ambient_sample: {}\n",
+				index.ambient_sample_count, index.first_ambient_sample,
+			));
+		}
+
+		if indices.is_empty() { to_write.push_str("\tlump is empty\n"); }
+	}
+
+	// LUMP_LIGHTING_HDR
+	to_write.push_str("\nLUMP_LIGHTING_HDR (index 53)\n");
+	if let VBSPLumpType::LightingHDR(lightings) = &ld[53] {
+		for (counter, lighting) in lightings.iter().enumerate() {
+			to_write.push_str(&format!(
+				"\t[light{counter}] r, g, b, exp: {}, {}, {}, {}\n",
+				lighting.r, lighting.g, lighting.b, lighting.exponent,
+			));
+		}
+
+		if lightings.is_empty() { to_write.push_str("\tlump is empty\n"); }
+	}
+
+	// LUMP_LEAF_AMBIENT_LIGHTING_HDR and LUMP_LEAF_AMBIENT_LIGHTING
+	for (lump_index, name, indices, samples) in [
+		(55, "LUMP_LEAF_AMBIENT_LIGHTING_HDR", bsp.ambient_index_hdr, bsp.ambient_lighting_hdr),
+		(56, "LUMP_LEAF_AMBIENT_LIGHTING", bsp.ambient_index, bsp.ambient_lighting),
+	] {
+		to_write.push_str(&format!("\n{name} (index {lump_index})\n"));
+		// the position is relative to the leaf that owns the sample
+		let mut owners: Vec<Option<usize>> = vec![None; samples.len()];
+		for (leaf, i) in indices.iter().enumerate() {
+			let first: usize = i.first_ambient_sample as usize;
+			let end: usize = (first + i.ambient_sample_count as usize).min(samples.len());
+			for owner in owners.iter_mut().take(end).skip(first) {
+				if owner.is_none() { *owner = Some(leaf); }
+			}
+		}
+		for (counter, sample) in samples.iter().enumerate() {
+			to_write.push_str(&format!(
+				"\t[sample{counter}] x, y, z: {}, {}, {}\n",
+				sample.x, sample.y, sample.z,
+			));
+			let owner: Option<usize> = owners[counter];
+			if let Some(leaf) = owner.and_then(|l| bsp.leaves.get(l)) {
+				to_write.push_str(&format!(
+					"\t\tleaf{}, world position: {}\n",
+					owner.unwrap(), ambient::sample_position(leaf, sample),
+				));
+			}
+			to_write.push_str(&format!(
+				"\t\tcube: {}\n",
+				sample.cube.color.iter()
+				.map(|c| format!("({}, {}, {}, {})", c.r, c.g, c.b, c.exponent))
+				.collect::<Vec<String>>()
+				.join(" "),
+			));
+		}
+
+		if samples.is_empty() { to_write.push_str("\tlump is empty\n"); }
+	}
+
+	// LUMP_FACES_HDR
+	to_write.push_str("\nLUMP_FACES_HDR (index 58)\n");
+	if let VBSPLumpType::FacesHDR(faces) = &ld[58] {
+		// everything else is the same as in the ldr faces (see the warnings below if not)
+		for (counter, face) in faces.iter().enumerate() {
+			to_write.push_str(&format!(
+				"\t[face{counter}]\n\t\ttexinfo: {}\n\t\tstyles: {:?}\n\t\tlight_offset: {} (light{})\n",
+				face.tex_info, face.styles, face.light_offset, face.light_offset / 4,
+			));
+			to_write.push_str(&format!(
+				"\t\tlightmap_texture_mins: ({}, {})\n\t\tlightmap_texture_size: ({}, {})\n",
+				face.lightmap_texture_mins[0], face.lightmap_texture_mins[1],
+				face.lightmap_texture_size[0], face.lightmap_texture_size[1],
+			));
+		}
+
+		if faces.is_empty() { to_write.push_str("\tlump is empty\n"); }
+	}
+	for problem in bsp.lighting_problems() {
+		to_write.push_str(&format!("\twarning: {problem}\n"));
+	}
+
 	// done!
 	println!(
 		"dumping finished! wrote {} bytes",
@@ -1028,6 +1212,7 @@ pub fn dump_goldsrc(
 	path: String,
 	header: file_structure::Header,
 	ld: Vec<GoldSrcLumpType>,
+	game_dir: Option<String>,
 ) {
 	let dump_gsrc_path: String = path.trim_end_matches(".bsp")
 	.to_owned() + "-bsp_dump.txt";
@@ -1107,15 +1292,14 @@ pub fn dump_goldsrc(
 			));
 			counter += 1;
 		}
+		dump_texture_sources(&mut to_write, &Q1Bsp::from_goldsrc(&ld, header.lumps[2].file_offset), &game_dir);
 	}
 
 	// LUMP_VERTICES
 	to_write.push_str("\nLUMP_VERTICES (index 3)\n");
 	if let GoldSrcLumpType::Vertices(verts) = &ld[3] {
-		let mut counter: u32 = 0;
-		for vert in verts {
+		for (counter, vert) in verts.iter().enumerate() {
 			to_write.push_str(&format!("\t[vert{counter}] {vert}\n"));
-			counter += 1;
 		}
 	}
 
@@ -1124,17 +1308,13 @@ pub fn dump_goldsrc(
 	if header.lumps[4].length == 0 {
 		to_write.push_str("\tlump empty\n");
 	} else {
-		to_write.push_str(&format!(
-			"\tstructure unknown, {} bytes\n",
-			header.lumps[4].length,
-		))
+		dump_leaf_vis(&mut to_write, &Q1Bsp::from_goldsrc(&ld, header.lumps[2].file_offset));
 	}
 
 	// LUMP_NODES
 	to_write.push_str("\nLUMP_NODES (index 5)\n");
 	if let GoldSrcLumpType::Nodes(nodes) = &ld[5] {
-		let mut counter: u32 = 0;
-		for node in nodes {
+		for (counter, node) in nodes.iter().enumerate() {
 			to_write.push_str(&format!("\t[node{counter}]\n"));
 			to_write.push_str(&format!(
 				"\t\tplane_idx: {}\n\t\tchildren_idxs: {}{}, {}{}\n\t\tmins: {:?}\n\t\tmaxs: {:?}\n",
@@ -1151,29 +1331,25 @@ pub fn dump_goldsrc(
 			to_write.push_str(&format!(
 				"\t\tfirst_face: {}\n\t\tnum_faces: {}\n", node.first_face, node.num_faces,
 			));
-			counter += 1;
 		}
 	}
 
 	// LUMP_TEXINFO
 	to_write.push_str("\nLUMP_TEXINFO (index 6)\n");
 	if let GoldSrcLumpType::TexInfo(texinfos) = &ld[6] {
-		let mut counter: u32 = 0;
-		for texinfo in texinfos {
+		for (counter, texinfo) in texinfos.iter().enumerate() {
 			to_write.push_str(&format!("\t[texinfo{counter}]\n"));
 			to_write.push_str(&format!(
 				"\t\ts, s_shift: {}, {}\n\t\tt, t_shift: {}, {}\n\t\tmiptex_idx: {}\n\t\tflags: {}\n",
 				texinfo.s, texinfo.s_shift, texinfo.t, texinfo.t_shift, texinfo.miptex_idx, texinfo.flags,
 			));
-			counter += 1;
 		}
 	}
 
 	// LUMP_FACES
 	to_write.push_str("\nLUMP_FACES (index 7)\n");
 	if let GoldSrcLumpType::Faces(faces) = &ld[7] {
-		let mut counter: u32 = 0;
-		for face in faces {
+		for (counter, face) in faces.iter().enumerate() {
 			to_write.push_str(&format!("\t[face{counter}]\n"));
 			to_write.push_str(&format!(
 				"\t\tplane_idx: {}\n\t\tplane_side: {}\n\t\tfirst_surfedge_idx: {}\n",
@@ -1183,38 +1359,32 @@ pub fn dump_goldsrc(
 				"\t\tnum_surfedges: {}\n\t\ttexinfo_idx: {}\n\t\tstyles: {:?}\n\t\tlightmap_offset: {}\n",
 				face.num_surfedges, face.texinfo_idx, face.styles, face.lightmap_offset,
 			));
-			counter += 1;
 		}
 	}
 
 	// LUMP_LIGHTING
 	to_write.push_str("\nLUMP_LIGHTING (index 8)\n");
 	if let GoldSrcLumpType::Lighting(lightmaps) = &ld[8] {
-		let mut counter: u32 = 0;
-		for light in lightmaps {
+		for (counter, light) in lightmaps.iter().enumerate() {
 			to_write.push_str(&format!("\t[lightmap{counter}] {:?}\n", light.color));
-			counter += 1;
 		}
 	}
 
 	// LUMP_CLIPNODES
 	to_write.push_str("\nLUMP_CLIPNODES (index 9)\n");
 	if let GoldSrcLumpType::ClipNodes(clipnodes) = &ld[9] {
-		let mut counter: u32 = 0;
-		for clipnode in clipnodes {
+		for (counter, clipnode) in clipnodes.iter().enumerate() {
 			to_write.push_str(&format!(
 				"\t[clnode{counter}]\n\t\tplane_idx: {}\n\t\tchildren_idxs: {:?}\n",
 				clipnode.plane_idx, clipnode.children_idxs,
 			));
-			counter += 1;
 		}
 	}
 
 	// LUMP_LEAVES
 	to_write.push_str("\nLUMP_LEAVES (index 10)\n");
 	if let GoldSrcLumpType::Leaves(leaves) = &ld[10] {
-		let mut counter: u32 = 0;
-		for leaf in leaves {
+		for (counter, leaf) in leaves.iter().enumerate() {
 			to_write.push_str(&format!("\t[leaf{counter}]\n"));
 			to_write.push_str(&format!(
 				"\t\tcontents: {}\n\t\tvis_offset: {}\n\t\tmins, maxs: {:?}, {:?}\n",
@@ -1225,27 +1395,22 @@ pub fn dump_goldsrc(
 				"\t\tfirst_marksurface_idx: {}\n\t\tnum_marksurfaces: {}\n\t\tambient_levels: {:?}\n",
 				leaf.first_maksurf_idx, leaf.num_marksurfaces, leaf.ambient_levels,
 			));
-			counter += 1;
 		}
 	}
 
 	// LUMP_MARKSURFACES
 	to_write.push_str("\nLUMP_MARKSURFACES (index 11)\n");
 	if let GoldSrcLumpType::MarkSurfaces(marksurfs) = &ld[11] {
-		let mut counter: u32 = 0;
-		for marksurf in marksurfs {
+		for (counter, marksurf) in marksurfs.iter().enumerate() {
 			to_write.push_str(&format!("\t[marksurf{counter}] {marksurf}\n"));
-			counter += 1;
 		}
 	}
 
 	// LUMP_EDGES
 	to_write.push_str("\nLUMP_EDGES (index 12)\n");
 	if let GoldSrcLumpType::Edges(edges) = &ld[12] {
-		let mut counter: u32 = 0;
-		for edge in edges {
+		for (counter, edge) in edges.iter().enumerate() {
 			to_write.push_str(&format!("\t[edge{counter}] {edge:?}\n"));
-			counter += 1;
 		}
 	}
 
@@ -1254,18 +1419,15 @@ pub fn dump_goldsrc(
 	to_write.push_str("positive number: edge referenced from 1st to 2nd vertex (->)\n");
 	to_write.push_str("negative number: edge referenced from 2nd to 1st vertex (<-)\n");
 	if let GoldSrcLumpType::SurfEdges(surfedges) = &ld[13] {
-		let mut counter: u32 = 0;
-		for surfedge in surfedges {
+		for (counter, surfedge) in surfedges.iter().enumerate() {
 			to_write.push_str(&format!("\t[surfedge{counter}] {surfedge}\n"));
-			counter += 1;
 		}
 	}
 
 	// LUMP_MODELS
 	to_write.push_str("\nLUMP_MODELS (index 14)\n");
 	if let GoldSrcLumpType::Models(models) = &ld[14] {
-		let mut counter: u32 = 0;
-		for model in models {
+		for (counter, model) in models.iter().enumerate() {
 			to_write.push_str(&format!("\t[model{counter}]\n"));
 			to_write.push_str(&format!(
 				"\t\tmins, maxs: {}, {}\n\t\torigin: {}\n\t\theadnodes_idx: {:?}\n\t\tnum_visleafs: {}\n",
@@ -1275,7 +1437,6 @@ pub fn dump_goldsrc(
 				"\t\tfirst_face_idx: {}\n\t\tnum_faces: {}\n",
 				model.first_face_idx, model.num_faces,
 			));
-			counter += 1;
 		}
 	}
 
@@ -1290,6 +1451,7 @@ pub fn dump_quake(
 	path: String,
 	header: file_structure::Header,
 	ld: Vec<QuakeLumpType>,
+	game_dir: Option<String>,
 ) {
 	let dump_quake_path: String = path.trim_end_matches(".bsp")
 	.to_owned() + "-bsp_dump.txt";
@@ -1373,15 +1535,14 @@ pub fn dump_quake(
 				miptex.name, miptex.width, miptex.height, miptex.offsets,
 			));
 		}
+		dump_texture_sources(&mut to_write, &Q1Bsp::from_quake(&ld, header.lumps[2].file_offset), &game_dir);
 	}
 
 	// LUMP_VERTICES
 	to_write.push_str("\nLUMP_VERTICES (index 3)\n");
 	if let QuakeLumpType::Vertices(verts) = &ld[3] {
-		let mut counter: u32 = 0;
-		for vert in verts {
+		for (counter, vert) in verts.iter().enumerate() {
 			to_write.push_str(&format!("\t[vert{counter}] {}\n", vert.point));
-			counter += 1;
 		}
 	}
 
@@ -1390,17 +1551,13 @@ pub fn dump_quake(
 	if header.lumps[4].length == 0 {
 		to_write.push_str("\tlump empty\n");
 	} else {
-		to_write.push_str(&format!(
-			"\tstructure unknown, {} bytes\n",
-			header.lumps[4].length,
-		))
+		dump_leaf_vis(&mut to_write, &Q1Bsp::from_quake(&ld, header.lumps[2].file_offset));
 	}
 
 	// LUMP_NODES
 	to_write.push_str("\nLUMP_NODES (index 5)\n");
 	if let QuakeLumpType::Nodes(nodes) = &ld[5] {
-		let mut counter: u32 = 0;
-		for node in nodes {
+		for (counter, node) in nodes.iter().enumerate() {
 			to_write.push_str(&format!("\t[node{counter}]\n"));
 			to_write.push_str(&format!("\t\tplanenum: {}\n", node.planenum));
 			let mut children_string: String = "\t\tchildren:".to_string();
@@ -1424,7 +1581,6 @@ pub fn dump_quake(
 				"\t\tfirst_faces: {}\n\t\tnum_faces: {}\n",
 				node.first_face, node.num_faces,
 			));
-			counter += 1;
 		}
 	}
 
@@ -1462,13 +1618,16 @@ pub fn dump_quake(
 
 	// LUMP_LIGHTING
 	to_write.push_str("\nLUMP_LIGHTING (index 8)\n");
-	if header.lumps[8].length == 0 {
-		to_write.push_str("\tlump empty\n");
-	} else {
-		to_write.push_str(&format!(
-			"\tstructure unknown, {} bytes\n",
-			header.lumps[8].length,
-		))
+	if let QuakeLumpType::Lighting(lighting) = &ld[8] {
+		if lighting.is_empty() {
+			to_write.push_str("\tlump empty\n");
+		} else {
+			// its only useful together with the faces, see the lightmaps command
+			to_write.push_str(&format!(
+				"\t{} bytes of grayscale lightmaps\n",
+				lighting.len(),
+			))
+		}
 	}
 
 	// LUMP_CLIPNODES
@@ -1561,4 +1720,66 @@ pub fn dump_quake(
 		"dumping finished! wrote {} bytes",
 		dump_quake.write(to_write.as_bytes()).unwrap(),
 	);
-}
\ No newline at end of file
+}
+
+// which textures are in the bsp and which ones come from the wads
+fn dump_texture_sources(to_write: &mut String, bsp: &Q1Bsp, game_dir: &Option<String>) {
+	let report: wad::TextureReport = wad::resolve(bsp, game_dir.as_deref().map(Path::new));
+	to_write.push_str("\twads:\n");
+	if game_dir.is_none() {
+		to_write.push_str(&format!("\t\t{} (not searched, no game directory given)\n", wad::wad_list(bsp).join(";")));
+	}
+	for (name, wad) in &report.wads {
+		match wad {
+			Ok(wad) => to_write.push_str(&format!(
+				"\t\t{name}: {} ({} textures)\n",
+				wad.path.display(), wad.num_textures(),
+			)),
+			Err(e) => to_write.push_str(&format!("\t\t{name}: {e}\n")),
+		}
+	}
+
+	to_write.push_str("\ttexture sources:\n");
+	let (mut embedded, mut from_wads, mut missing, mut not_searched): (usize, usize, usize, usize) = (0, 0, 0, 0);
+	for (miptex, source) in bsp.miptexs.iter().zip(&report.sources) {
+		if miptex.name.is_empty() { continue; }
+		let location: String = match source {
+			wad::TextureSource::Embedded => { embedded += 1; "embedded".to_string() },
+			wad::TextureSource::Wad(i, _) => { from_wads += 1; report.wads[*i].0.clone() },
+			wad::TextureSource::Missing => { missing += 1; "missing!".to_string() },
+			wad::TextureSource::NotSearched => { not_searched += 1; "in a wad".to_string() },
+		};
+		to_write.push_str(&format!("\t\t{}: {location}\n", miptex.name));
+	}
+	match game_dir {
+		Some(_) => to_write.push_str(&format!("\t{embedded} embedded, {from_wads} found in wads, {missing} missing\n")),
+		None => to_write.push_str(&format!("\t{embedded} embedded, {not_searched} in wads\n")),
+	}
+}
+
+// quake and goldsrc vis is per leaf, see Q1Bsp::leaf_pvs
+fn dump_leaf_vis(to_write: &mut String, bsp: &Q1Bsp) {
+	to_write.push_str(&format!(
+		"\t{} bytes compressed, num_visleafs: {}\n",
+		bsp.visibility.len(), bsp.num_visleafs(),
+	));
+	for (counter, row) in bsp.pvs_matrix().iter().enumerate() {
+		let leaf: usize = counter + 1;
+		to_write.push_str(&format!(
+			"\t[leaf{leaf}] visofs: {}, {} visible\n",
+			bsp.leaves.get(leaf).map_or(-1, |l| l.visofs), row.count_ones(),
+		));
+		if !bsp.leaf_can_see(leaf, leaf) {
+			to_write.push_str("\t\twarning: leaf can't see itself\n");
+		}
+		to_write.push_str("\t\tvisible leaves:\n\t\t\t");
+		let visible: Vec<String> = row.ones()
+			.map(|i| (i + 1).to_string())
+			.collect();
+		for (i, chunk) in visible.chunks(25).enumerate() {
+			if i > 0 { to_write.push_str("\n\t\t\t"); }
+			to_write.push_str(&chunk.join(", "));
+		}
+		to_write.push('\n');
+	}
+}
@@ -1,6 +1,7 @@
 use crate::lumps::lumptype::Lumps;
 
 #[derive(Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum BSPVersion {
 	None,
 	VBSP, GoldSrc,
@@ -16,7 +17,7 @@ pub struct BSPFile {
 impl BSPFile {
 	pub fn new(header: Header) -> BSPFile {
 		BSPFile {
-			header: header,
+			header,
 			lump_data: match header.bspver {
 				BSPVersion::VBSP => Lumps::VBSP(vec![]),
 				BSPVersion::GoldSrc => Lumps::GoldSrc(vec![]),
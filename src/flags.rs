@@ -90,4 +90,60 @@ bitflags! {
 		const CurrentDown = -14;
 		const Translucent = -15;
 	}
-}
\ No newline at end of file
+}
+bitflags! {
+	#[derive(Debug, Clone, Copy)]
+	pub struct VtfFlags : u32 {
+		const PointSample       = 0x1;
+		const Trilinear         = 0x2;
+		const ClampS            = 0x4;
+		const ClampT            = 0x8;
+		const Anisotropic       = 0x10;
+		const HintDxt5          = 0x20;
+		const PwlCorrected      = 0x40;
+		const Normal            = 0x80;
+		const NoMip             = 0x100;
+		const NoLod             = 0x200;
+		const AllMips           = 0x400;
+		const Procedural        = 0x800;
+		const OneBitAlpha       = 0x1000;
+		const EightBitAlpha     = 0x2000;
+		const EnvMap            = 0x4000;
+		const RenderTarget      = 0x8000;
+		const DepthRenderTarget = 0x10000;
+		const NoDebugOverride   = 0x20000;
+		const SingleCopy        = 0x40000;
+		const PreSrgb           = 0x80000;
+		const NoDepthBuffer     = 0x800000;
+		const ClampU            = 0x2000000;
+		const VertexTexture     = 0x4000000;
+		const SsBump            = 0x8000000;
+		const Border            = 0x20000000;
+	}
+}
+
+bitflags! {
+	#[derive(Debug, Clone, Copy)]
+	pub struct MdlFlags : u32 {
+		const AutogeneratedHitbox         = 0x1;
+		const UsesEnvCubemap              = 0x2;
+		const ForceOpaque                 = 0x4;
+		const TranslucentTwoPass          = 0x8;
+		const StaticProp                  = 0x10;
+		const UsesFbTexture               = 0x20;
+		const HasShadowLod                = 0x40;
+		const UsesBumpMapping             = 0x80;
+		const UseShadowLodMaterials       = 0x100;
+		const Obsolete                    = 0x200;
+		const Unused                      = 0x400;
+		const NoForcedFade                = 0x800;
+		const ForcePhonemeCrossfade       = 0x1000;
+		const ConstantDirectionalLightDot = 0x2000;
+		const FlexesConverted             = 0x4000;
+		const BuiltInPreviewMode          = 0x8000;
+		const AmbientBoost                = 0x10000;
+		const DoNotCastShadows            = 0x20000;
+		const CastTextureShadows          = 0x40000;
+		const VertAnimFixedPointScale     = 0x200000;
+	}
+}
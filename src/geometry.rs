@@ -0,0 +1,113 @@
+// polygon helpers shared by the tools that need actual geometry
+// mostly the same stuff as the winding code in qbsp/vbsp
+
+use crate::utils::Vector3;
+
+pub const ON_EPSILON: f32 = 0.01;
+
+// big enough to cover any map in every engine we support
+const MAX_COORD: f32 = 131072.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Plane {
+	pub normal: Vector3,
+	pub dist: f32,
+}
+
+impl Plane {
+	pub fn distance_to(&self, point: &Vector3) -> f32 {
+		self.normal.dot(point) - self.dist
+	}
+
+	pub fn flipped(&self) -> Plane {
+		Plane { normal: -self.normal, dist: -self.dist }
+	}
+
+	pub fn is_same(&self, other: &Plane) -> bool {
+		(self.normal - other.normal).length() < 0.001
+		&& (self.dist - other.dist).abs() < ON_EPSILON
+	}
+}
+
+// a huge quad lying on the plane, wound clockwise when looking against the normal
+pub fn base_winding(plane: &Plane) -> Vec<Vector3> {
+	// find the major axis so we can pick a vector thats not parallel to the normal
+	let n: [f32; 3] = plane.normal.to_array();
+	let mut axis: usize = 0;
+	for i in 1..3 {
+		if n[i].abs() > n[axis].abs() { axis = i; }
+	}
+	let mut up: Vector3 = if axis == 2 {
+		Vector3 { x: 1.0, y: 0.0, z: 0.0 }
+	} else {
+		Vector3 { x: 0.0, y: 0.0, z: 1.0 }
+	};
+
+	up = (up - plane.normal * up.dot(&plane.normal)).normalized();
+	let org: Vector3 = plane.normal * plane.dist;
+	let right: Vector3 = up.cross(&plane.normal) * MAX_COORD;
+	up = up * MAX_COORD;
+
+	vec![
+		org - right + up,
+		org + right + up,
+		org + right - up,
+		org - right - up,
+	]
+}
+
+// keeps the part of the winding that is behind the plane
+pub fn clip_winding(winding: &[Vector3], plane: &Plane) -> Vec<Vector3> {
+	let dists: Vec<f32> = winding.iter().map(|p| plane.distance_to(p)).collect();
+	if dists.iter().all(|d| *d <= ON_EPSILON) { return winding.to_vec(); }
+	if dists.iter().all(|d| *d >= -ON_EPSILON) { return vec![]; }
+
+	let mut res: Vec<Vector3> = vec![];
+	for i in 0..winding.len() {
+		let p1: Vector3 = winding[i];
+		let d1: f32 = dists[i];
+		if d1 <= ON_EPSILON { res.push(p1); }
+
+		let j: usize = (i + 1) % winding.len();
+		let d2: f32 = dists[j];
+		if (d1 > ON_EPSILON && d2 < -ON_EPSILON) || (d1 < -ON_EPSILON && d2 > ON_EPSILON) {
+			let frac: f32 = d1 / (d1 - d2);
+			res.push(p1 + (winding[j] - p1) * frac);
+		}
+	}
+
+	res
+}
+
+pub fn winding_area(winding: &[Vector3]) -> f32 {
+	let mut total: Vector3 = Vector3::new();
+	for i in 2..winding.len() {
+		total = total + (winding[i - 1] - winding[0]).cross(&(winding[i] - winding[0]));
+	}
+	total.length() * 0.5
+}
+
+pub fn winding_center(winding: &[Vector3]) -> Vector3 {
+	let mut sum: Vector3 = Vector3::new();
+	for p in winding { sum = sum + *p; }
+	sum * (1.0 / winding.len().max(1) as f32)
+}
+
+// builds the faces of the convex volume enclosed by the planes
+// (normals point outwards), planes that end up without a face are dropped
+pub fn brush_windings(planes: &[Plane]) -> Vec<(Plane, Vec<Vector3>)> {
+	let mut res: Vec<(Plane, Vec<Vector3>)> = vec![];
+	for (i, plane) in planes.iter().enumerate() {
+		if planes[..i].iter().any(|p| p.is_same(plane)) { continue; }
+		let mut w: Vec<Vector3> = base_winding(plane);
+		for (j, other) in planes.iter().enumerate() {
+			if i == j || other.is_same(plane) { continue; }
+			w = clip_winding(&w, other);
+			if w.is_empty() { break; }
+		}
+		if w.len() >= 3 && winding_area(&w) > ON_EPSILON {
+			res.push((*plane, w));
+		}
+	}
+	res
+}
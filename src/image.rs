@@ -0,0 +1,98 @@
+// minimal image writers so we dont need any image crates
+// png is written with stored (uncompressed) deflate blocks, its big but it works everywhere
+
+use std::{fs, io::Write};
+
+pub fn write_png(path: &str, width: u32, height: u32, rgba: &[u8]) {
+	// png has no way to store an empty image
+	if width == 0 || height == 0 {
+		println!("not writing {path}, image is {width}x{height}");
+		return;
+	}
+	// every scanline starts with its filter type (0 = none)
+	let mut raw: Vec<u8> = Vec::with_capacity((width as usize * 4 + 1) * height as usize);
+	for row in rgba.chunks(width as usize * 4) {
+		raw.push(0);
+		raw.extend_from_slice(row);
+	}
+
+	let mut ihdr: Vec<u8> = vec![];
+	ihdr.extend_from_slice(&width.to_be_bytes());
+	ihdr.extend_from_slice(&height.to_be_bytes());
+	ihdr.extend_from_slice(&[8, 6, 0, 0, 0]); // 8 bit rgba, no interlacing
+
+	let mut png: Vec<u8> = b"\x89PNG\r\n\x1a\n".to_vec();
+	png_chunk(&mut png, b"IHDR", &ihdr);
+	png_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+	png_chunk(&mut png, b"IEND", &[]);
+
+	let mut out: fs::File = fs::File::create(path).unwrap();
+	out.write_all(&png).unwrap();
+}
+
+fn png_chunk(png: &mut Vec<u8>, name: &[u8; 4], data: &[u8]) {
+	png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+	let start: usize = png.len();
+	png.extend_from_slice(name);
+	png.extend_from_slice(data);
+	let crc: u32 = crc32(&png[start..]);
+	png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+	let mut res: Vec<u8> = vec![0x78, 0x01];
+	let mut chunks = data.chunks(65535).peekable();
+	if chunks.peek().is_none() { res.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]); }
+	while let Some(chunk) = chunks.next() {
+		res.push(if chunks.peek().is_none() { 1 } else { 0 });
+		res.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+		res.extend_from_slice(&(!(chunk.len() as u16)).to_le_bytes());
+		res.extend_from_slice(chunk);
+	}
+
+	let (mut a, mut b): (u32, u32) = (1, 0);
+	for byte in data {
+		a = (a + *byte as u32) % 65521;
+		b = (b + a) % 65521;
+	}
+	res.extend_from_slice(&((b << 16) | a).to_be_bytes());
+	res
+}
+
+// the standard crc32 (same one zip uses)
+pub fn crc32(data: &[u8]) -> u32 {
+	let mut crc: u32 = 0xFFFFFFFF;
+	for byte in data {
+		crc ^= *byte as u32;
+		for _ in 0..8 {
+			crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+		}
+	}
+	!crc
+}
+
+// radiance .hdr with flat (not run length encoded) scanlines
+pub fn write_hdr(path: &str, width: u32, height: u32, rgb: &[[f32; 3]]) {
+	let mut res: Vec<u8> = format!(
+		"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {height} +X {width}\n"
+	).into_bytes();
+	for pixel in rgb {
+		let max: f32 = pixel[0].max(pixel[1]).max(pixel[2]);
+		if max < 1e-32 {
+			res.extend_from_slice(&[0, 0, 0, 0]);
+			continue;
+		}
+		// frexp, the mantissa ends up in [0.5, 1)
+		let exp: i32 = max.log2().floor() as i32 + 1;
+		let scale: f32 = 256.0 / 2f32.powi(exp);
+		res.extend_from_slice(&[
+			(pixel[0] * scale).min(255.0) as u8,
+			(pixel[1] * scale).min(255.0) as u8,
+			(pixel[2] * scale).min(255.0) as u8,
+			(exp + 128).clamp(0, 255) as u8,
+		]);
+	}
+
+	let mut out: fs::File = fs::File::create(path).unwrap();
+	out.write_all(&res).unwrap();
+}
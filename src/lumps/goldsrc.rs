@@ -5,13 +5,14 @@ use crate::utils::Vector3;
 use crate::flags::GoldSrcContentsFlags;
 
 #[derive(Debug, Clone)]
+#[allow(unused)]
 pub enum GoldSrcLumpType {
 	None,
 	Entities(Vec<Vec<(String, String)>>),
 	Planes(Vec<Plane>),
 	Textures(Textures),
 	Vertices(Vec<Vector3>),
-	Visibility,
+	Visibility(Vec<u8>), // still compressed, see Q1Bsp::pvs_matrix
 	Nodes(Vec<Node>),
 	TexInfo(Vec<TexInfo>),
 	Faces(Vec<Face>),
@@ -49,6 +50,11 @@ pub struct Miptex {
 	// zeroes if the texture is stored in the wad file
 	// if not zero, offsets from the beginning of this struct to the mipmap
 	pub offsets: [u32; 4],
+
+	// 8 bit palette indices for each of the 4 mip levels, empty if the texture is in a wad
+	pub mips: Vec<Vec<u8>>,
+	// comes right after the last mip, 256 colors
+	pub palette: Vec<[u8; 3]>,
 }
 
 #[derive(Debug, Clone)]
@@ -3,6 +3,7 @@ use crate::lumps::goldsrc::GoldSrcLumpType;
 use crate::lumps::quake::QuakeLumpType;
 
 #[derive(Debug, Clone)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Lumps {
 	VBSP(Vec<VBSPLumpType>),
 	GoldSrc(Vec<GoldSrcLumpType>),
@@ -5,17 +5,18 @@ use crate::utils::Vector3;
 use crate::flags::GoldSrcContentsFlags; // matches quake 1 flags perfectly
 
 #[derive(Debug, Clone)]
+#[allow(unused)]
 pub enum QuakeLumpType {
 	None,
 	Entities(Vec<Vec<(String, String)>>),
 	Planes(Vec<Plane>),
 	Textures(Texture),
 	Vertices(Vec<Vertex>),
-	Visibility,
+	Visibility(Vec<u8>), // still compressed, see Q1Bsp::pvs_matrix
 	Nodes(Vec<Node>),
 	TexInfo(Vec<TexInfo>),
 	Faces(Vec<Face>),
-	Lighting,
+	Lighting(Vec<u8>), // one byte per luxel
 	ClipNodes(Vec<ClipNode>),
 	Leaves(Vec<Leaf>),
 	MarkSurfaces(Vec<u16>),
@@ -46,6 +47,7 @@ pub struct Miptex {
 	pub width: u32,
 	pub height: u32,
 	pub offsets: [u32; 4], // this one is definitely hardcoded
+	pub mips: Vec<Vec<u8>>, // indices into the palette (palette.lmp in the game's pak)
 }
 
 #[derive(Debug, Clone)]
@@ -65,7 +67,7 @@ pub struct Node {
 
 #[derive(Debug, Clone)]
 pub struct TexInfo {
-	pub vecs: [[f32; 2]; 4],
+	pub vecs: [[f32; 4]; 2], // [s, t] each being [x, y, z, offset]
 	pub miptex: i32,
 	pub flags: i32, // TODO: define the one flag lmao
 }
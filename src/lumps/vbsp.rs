@@ -10,6 +10,7 @@ use crate:: {
 		physcol_data,
 		occlusion,
 		gamelump,
+		vis,
 	},
 	flags::{
 		ContentsFlags,
@@ -42,8 +43,8 @@ pub enum VBSPLumpType {
 	SurfEdges(Vec<i32>),
 	Models(Vec<Model>),
 	WorldLights,
-	LeafFaces,
-	LeafBrushes,
+	LeafFaces(Vec<u16>), // indices into faces, leaves point into this
+	LeafBrushes(Vec<u16>), // indices into brushes, leaves point into this
 	Brushes(Vec<Brush>),
 	BrushSides(Vec<BrushSide>),
 	Areas(Vec<Area>),
@@ -92,12 +93,12 @@ pub enum VBSPLumpType {
 	LeafAmbientIndexHDR(Vec<LeafAmbientIndex>),
 	LightmapPageInfos,
 	LeafAmbientIndex(Vec<LeafAmbientIndex>),
-	LightingHDR,
+	LightingHDR(Vec<ColorRGBExp32>),
 	WorldLightsHDR,
 	LeafAmbientLightingHDR(Vec<LeafAmbientLighting>),
 	LeafAmbientLighting(Vec<LeafAmbientLighting>),
 	XZipPakFile,
-	FacesHDR,
+	FacesHDR(Vec<Face>),
 	MapFlags,
 	OverlayFades,
 	OverlaySystemLevels,
@@ -218,6 +219,7 @@ pub struct Model {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[allow(unused)]
 pub struct WorldLight {
 
 }
@@ -267,9 +269,45 @@ pub struct Vis {
 	// so ill have to use a vec
 	pub byte_offsets: Vec<[i32; 2]>,
 
-	// here ill store the decompressed PVS and PAS data
-	// that is the clusters visible and audible from each cluster
-	pub cluster_data: [Vec<Vec<bool>>; 2]
+	// the whole lump, still compressed
+	// rows only get decompressed when they're asked for
+	pub data: Vec<u8>,
+}
+
+impl Vis {
+	// the clusters visible from a cluster
+	pub fn pvs(&self, cluster: i32) -> vis::BitSet {
+		vis::decompress_vis(self.row(cluster, 0), self.num_clusters.max(0) as usize)
+	}
+
+	// the clusters audible from a cluster
+	pub fn pas(&self, cluster: i32) -> vis::BitSet {
+		vis::decompress_vis(self.row(cluster, 1), self.num_clusters.max(0) as usize)
+	}
+
+	// out of range clusters (like -1 for solid leaves) cant see or hear anything
+	pub fn can_see(&self, from: i32, to: i32) -> bool {
+		self.lookup(0, from, to)
+	}
+
+	pub fn can_hear(&self, from: i32, to: i32) -> bool {
+		self.lookup(1, from, to)
+	}
+
+	// only decodes the row up to the bit that is asked for
+	fn lookup(&self, kind: usize, from: i32, to: i32) -> bool {
+		if to < 0 || to >= self.num_clusters { return false; }
+		vis::decompress_vis(self.row(from, kind), to as usize + 1).get(to as usize)
+	}
+
+	// compressed pvs (kind 0) or pas (kind 1) of a cluster,
+	// offsets outside of the lump decode to nothing visible
+	fn row(&self, cluster: i32, kind: usize) -> &[u8] {
+		let Some(offsets) = usize::try_from(cluster).ok().and_then(|c| self.byte_offsets.get(c)) else { return &[] };
+		usize::try_from(offsets[kind]).ok()
+			.and_then(|ofs| self.data.get(ofs..))
+			.unwrap_or(&[])
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -338,6 +376,7 @@ pub struct CompressedLightCube {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[allow(unused)]
 pub struct LeafAmbientLighting {
 	pub cube: CompressedLightCube,
 	pub x: u8, pub y: u8, pub z: u8,
@@ -345,6 +384,7 @@ pub struct LeafAmbientLighting {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[allow(unused)]
 pub struct LeafAmbientIndex {
 	pub ambient_sample_count: u16,
 	pub first_ambient_sample: u16,
@@ -367,8 +407,7 @@ pub struct PhysModel {
 	pub keydata_size: i32, // size of text section
 	pub solid_count: i32, // number of collision data sections
 	pub collision_data: Vec<physcol_data::CollisionData>,
-	// key {same format as entity string}
-	pub key_data: Vec<(String, Vec<(String, String)>)>,
+	pub key_data: physcol_data::KeyData,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -391,6 +430,7 @@ pub struct VertexNormalIndex {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[allow(unused)]
 pub struct DispLightmapSamplePosition {
 	pub unknown: u8, // no clue
 }
@@ -429,6 +469,7 @@ pub struct ClipPortalVert {
 }
 
 #[derive(Debug, Clone)]
+#[allow(unused)]
 pub struct TexDataStringData {
 	pub val: String,
 	// ill store the offset so that i can use it with
@@ -6,29 +6,237 @@ mod parse;
 mod dump;
 mod specific;
 mod flags;
+mod geometry;
+mod image;
+mod tools;
 
 use std::{fs, env};
 use reader::Reader;
+use file_structure::BSPFile;
+use lumps::lumptype::Lumps;
 
 const VERSION: &str = "v0.0.1";
 
 const VBSP_MAGIC: i32 = 0x50534256;
+#[allow(unused)] // TODO: quake 2
 const IBSP_MAGIC: i32 = 0x50534249;
 const GOLDSRC_MAGIC: i32 = 0x0000001e;
 const QUAKE_MAGIC: i32 = 0x0000001d;
 
+const USAGE: &str = "usage:
+	bsp_dump <bsp file> [game dir]
+	bsp_dump decompile <bsp file>
+	bsp_dump collision <bsp file> [obj|gltf]
+	bsp_dump lightmaps <bsp file> [png|hdr]
+	bsp_dump miptex <bsp file> [game dir] [palette.lmp]
+	bsp_dump materials <bsp file> [game dir]
+	bsp_dump vtf <bsp or vtf file>
+	bsp_dump cubemaps <bsp file>
+	bsp_dump props <bsp file> [game dir]
+	bsp_dump textures <bsp file> [table|json]
+	bsp_dump limits <bsp file>
+	bsp_dump validate <bsp file>
+	bsp_dump crc <bsp file>
+	bsp_dump ambient <bsp file> [hdr] [x y z]
+	bsp_dump locate <bsp file> <x y z>
+	bsp_dump areas <bsp file> [dot|json]
+	bsp_dump tree <bsp file> [dot|json]
+	bsp_dump visible <bsp file> <x y z> [x y z]
+	bsp_dump trace <bsp file> <x y z> <x y z> [hull <0-3>] [box <mins> <maxs>] [mask <name>]";
+
 fn main() {
-    let args: Vec<String> = env::args()
+	let args: Vec<String> = env::args()
 	.collect();
 
-	let file: Vec<u8> = fs::read(args[1].clone())
+	if args.len() < 2 {
+		println!("{USAGE}");
+		std::process::exit(0);
+	}
+
+	match args[1].as_str() {
+		"decompile" => {
+			let path: String = get_arg(&args, 2);
+			let file: BSPFile = read_file(&path);
+			match tools::q1bsp::Q1Bsp::from_file(&file) {
+				Some(bsp) => tools::decompile::decompile(path, &bsp),
+				None => println!("decompiling is only supported for goldsrc and quake maps"),
+			}
+		},
+		"collision" => {
+			let path: String = get_arg(&args, 2);
+			let format: String = args.get(3).cloned().unwrap_or("obj".to_string());
+			let file: BSPFile = read_file(&path);
+			match &file.lump_data {
+				Lumps::VBSP(ld) => tools::collision::export(path, ld, &format),
+				_ => println!("collision export is only supported for source maps"),
+			}
+		},
+		"lightmaps" => {
+			let path: String = get_arg(&args, 2);
+			let format: tools::lightmap::AtlasFormat = match args.get(3).map(|s| s.as_str()) {
+				None | Some("png") => tools::lightmap::AtlasFormat::Png,
+				Some("hdr") => tools::lightmap::AtlasFormat::Hdr,
+				Some(other) => {
+					println!("unknown lightmap format {other}! (expected png or hdr)");
+					std::process::exit(0);
+				},
+			};
+			let file: BSPFile = read_file(&path);
+			match &file.lump_data {
+				Lumps::VBSP(ld) => tools::lightmap::export_vbsp(path, ld, format),
+				_ => tools::lightmap::export_q1(path, &tools::q1bsp::Q1Bsp::from_file(&file).unwrap(), format),
+			}
+		},
+		"miptex" => {
+			let path: String = get_arg(&args, 2);
+			let file: BSPFile = read_file(&path);
+			match tools::q1bsp::Q1Bsp::from_file(&file) {
+				Some(bsp) => {
+					// either one can be left out, palettes are told apart by their extension
+					let palette: Option<&str> = args[3..].iter().find(|a| a.ends_with(".lmp")).map(|s| s.as_str());
+					let game_dir: Option<&str> = args[3..].iter().find(|a| !a.ends_with(".lmp")).map(|s| s.as_str());
+					tools::miptex::export(path, &bsp, game_dir, palette);
+				},
+				None => println!("source maps dont have miptex textures"),
+			}
+		},
+		"materials" => {
+			let path: String = get_arg(&args, 2);
+			let file: BSPFile = read_file(&path);
+			match &file.lump_data {
+				Lumps::VBSP(ld) => tools::materials::check(ld, args.get(3).map(|s| s.as_str())),
+				_ => println!("materials only exist in source maps, use miptex for goldsrc and quake"),
+			}
+		},
+		"vtf" => {
+			let path: String = get_arg(&args, 2);
+			if path.to_lowercase().ends_with(".vtf") {
+				tools::vtf::export_file(path);
+				return;
+			}
+			let file: BSPFile = read_file(&path);
+			match &file.lump_data {
+				Lumps::VBSP(ld) => tools::vtf::export_pak(path, ld),
+				_ => println!("only source maps have vtfs in their pakfile"),
+			}
+		},
+		"cubemaps" => {
+			let path: String = get_arg(&args, 2);
+			let file: BSPFile = read_file(&path);
+			match &file.lump_data {
+				Lumps::VBSP(ld) => tools::cubemaps::check(path, ld),
+				_ => println!("cubemaps only exist in source maps"),
+			}
+		},
+		"props" => {
+			let path: String = get_arg(&args, 2);
+			let file: BSPFile = read_file(&path);
+			match &file.lump_data {
+				Lumps::VBSP(ld) => tools::props::check(ld, args.get(3).map(|s| s.as_str())),
+				_ => println!("static props only exist in source maps"),
+			}
+		},
+		"textures" => {
+			let path: String = get_arg(&args, 2);
+			let format: String = args.get(3).cloned().unwrap_or("table".to_string());
+			let file: BSPFile = read_file(&path);
+			tools::textures::report(path, &file, &format);
+		},
+		"limits" => {
+			let path: String = get_arg(&args, 2);
+			tools::limits::report(&read_file(&path));
+		},
+		"validate" => {
+			let path: String = get_arg(&args, 2);
+			std::process::exit(tools::validate::validate(&path));
+		},
+		"crc" => {
+			let path: String = get_arg(&args, 2);
+			tools::crc::print_crc(&path);
+		},
+		"ambient" => {
+			let path: String = get_arg(&args, 2);
+			let hdr: bool = args.get(3).is_some_and(|s| s == "hdr");
+			let first: usize = if hdr { 4 } else { 3 };
+			let point: Option<utils::Vector3> = if args.len() > first { Some(get_point(&args, first)) } else { None };
+			let file: BSPFile = read_file(&path);
+			match (&file.lump_data, point) {
+				(Lumps::VBSP(ld), Some(point)) => tools::ambient::print_cube_at(ld, point, hdr),
+				(Lumps::VBSP(ld), None) => tools::ambient::export(path, ld, hdr),
+				_ => println!("ambient lighting is only in source maps"),
+			}
+		},
+		"locate" => {
+			let path: String = get_arg(&args, 2);
+			let point: utils::Vector3 = get_point(&args, 3);
+			tools::locate::locate(&read_file(&path), point);
+		},
+		"areas" => {
+			let path: String = get_arg(&args, 2);
+			let format: String = args.get(3).cloned().unwrap_or("dot".to_string());
+			let file: BSPFile = read_file(&path);
+			match &file.lump_data {
+				Lumps::VBSP(ld) => tools::areas::export(path, ld, &format),
+				_ => println!("areas only exist in source maps"),
+			}
+		},
+		"tree" => {
+			let path: String = get_arg(&args, 2);
+			let format: String = args.get(3).cloned().unwrap_or("dot".to_string());
+			let file: BSPFile = read_file(&path);
+			tools::tree::export(path, &file, &format);
+		},
+		"visible" => {
+			let path: String = get_arg(&args, 2);
+			let a: utils::Vector3 = get_point(&args, 3);
+			let b: Option<utils::Vector3> = if args.len() > 6 { Some(get_point(&args, 6)) } else { None };
+			let file: BSPFile = read_file(&path);
+			match b {
+				Some(b) => tools::visible::between(&file, a, b),
+				None => tools::visible::entities_from(&file, a),
+			}
+		},
+		"trace" => {
+			let path: String = get_arg(&args, 2);
+			let start: utils::Vector3 = get_point(&args, 3);
+			let end: utils::Vector3 = get_point(&args, 6);
+			tools::trace::run(&read_file(&path), start, end, &args[9..]);
+		},
+		_ => {
+			let file: BSPFile = read_file(&args[1]);
+			dump::dump(args[1].clone(), file, args.get(2).cloned())
+		},
+	}
+}
+
+fn get_arg(args: &[String], index: usize) -> String {
+	args.get(index)
+	.cloned()
+	.unwrap_or_else(|| {
+		println!("not enough arguments!\n{USAGE}");
+		std::process::exit(0);
+	})
+}
+
+// 3 numbers starting at index
+fn get_point(args: &[String], index: usize) -> utils::Vector3 {
+	let Some(coords) = args.get(index..index + 3) else {
+		println!("expected a point as 3 numbers!\n{USAGE}");
+		std::process::exit(0);
+	};
+	utils::parse_vector(&coords.join(" ")).unwrap_or_else(|| {
+		println!("invalid point {}!", coords.join(" "));
+		std::process::exit(0);
+	})
+}
+
+fn read_file(path: &str) -> BSPFile {
+	let file: Vec<u8> = fs::read(path)
 	.unwrap_or_else(|e| {
 		println!("error while opening file: {e}");
 		std::process::exit(0);
 	});
 
 	let mut reader: Reader = Reader::new(file);
-	let file = parse::parse_file(&mut reader);
-
-	dump::dump(args[1].clone(), file)
+	parse::parse_file(&mut reader)
 }
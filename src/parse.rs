@@ -3,8 +3,8 @@ use crate::{
 	file_structure::{BSPFile, BSPVersion, Header, LumpInfo}, flags::{self, ContentsFlags, SurfaceFlags}, lumps::{
 		goldsrc::{self, GoldSrcLumpType}, lumptype::Lumps, quake::{self, QuakeLumpType}, vbsp::{self, VBSPLumpType}
 	}, reader::Reader, specific::{
-		gamelump, occlusion, physcol_data::{self, ModelHeaders}, vis::decompress_vis
-	}, utils::{parse_entity_string, Vector3}, GOLDSRC_MAGIC, IBSP_MAGIC, VBSP_MAGIC, QUAKE_MAGIC
+		gamelump, occlusion, physcol_data::{self, ModelHeaders}, ivp
+	}, utils::{parse_entity_string, Vector3}, GOLDSRC_MAGIC, VBSP_MAGIC, QUAKE_MAGIC
 };
 
 pub fn parse_file(
@@ -133,31 +133,27 @@ pub fn parse_vbsp_data_lumps(
 	info = &lump_info[current_index];
 	reader.index = info.file_offset as usize;
 
+	let lump_bytes: Vec<u8> = reader.bytes
+		.get(info.file_offset as usize..(info.file_offset + info.length) as usize)
+		.unwrap_or(&[])
+		.to_vec();
 	let mut vis: vbsp::Vis = vbsp::Vis {
-		num_clusters: reader.read_int(),
+		num_clusters: if info.length >= 4 { reader.read_int() } else { 0 },
 		byte_offsets: vec![],
-		cluster_data: [vec![], vec![]],
+		data: vec![],
 	};
-	for _ in 0..vis.num_clusters {
-		let pvs_ofs: i32 = reader.read_int();
-		let pas_ofs: i32 = reader.read_int();
-		vis.cluster_data[0].push(
-			decompress_vis(
-				&reader.bytes[info.file_offset as usize + pvs_ofs as usize..],
-				&vis.num_clusters,
-			)
-		);
-		vis.cluster_data[1].push(
-			decompress_vis(
-				&reader.bytes[info.file_offset as usize + pas_ofs as usize..],
-				&vis.num_clusters,
-			)
-		);
-		vis.byte_offsets.push(
-			[pvs_ofs, pas_ofs]
-		);
-	}
-	println!("parsed and decompressed visibility lump! ({current_index})");
+	// dont trust num_clusters further than the offset table that actually fits in the lump
+	let max_clusters: i32 = (lump_bytes.len().saturating_sub(4) / 8) as i32;
+	if vis.num_clusters < 0 || vis.num_clusters > max_clusters {
+		println!("visibility lump says it has {} clusters but only {max_clusters} fit!", vis.num_clusters);
+		vis.num_clusters = vis.num_clusters.clamp(0, max_clusters);
+	}
+	let num_clusters: usize = vis.num_clusters as usize;
+	for _ in 0..num_clusters {
+		vis.byte_offsets.push([reader.read_int(), reader.read_int()]);
+	}
+	vis.data = lump_bytes;
+	println!("parsed visibility lump! ({current_index})");
 	lump_data.push(VBSPLumpType::Visibility(vis));
 
 	//      ====LUMP_NODES====
@@ -223,25 +219,7 @@ pub fn parse_vbsp_data_lumps(
 
 	let mut faces: Vec<vbsp::Face> = vec![];
 	while reader.index < (info.file_offset + info.length) as usize {
-		faces.push(vbsp::Face {
-			plane_num: reader.read_ushort(),
-			side: reader.read_byte(),
-			on_node: reader.read_byte(),
-			first_edge: reader.read_uint(),
-			num_edges: reader.read_short(),
-			tex_info: reader.read_short(),
-			disp_info: reader.read_short(),
-			surface_fog_volume_id: reader.read_short(),
-			styles: reader.read_int().to_le_bytes(), // lmao
-			light_offset: reader.read_int(),
-			area: reader.read_float(),
-			lightmap_texture_mins: [reader.read_int(), reader.read_int()],
-			lightmap_texture_size: [reader.read_int(), reader.read_int()],
-			orig_face: reader.read_int(),
-			num_prims: reader.read_ushort(),
-			first_prim_id: reader.read_ushort(),
-			smoothing_groups: reader.read_uint(),
-		});
+		faces.push(reader.read_face());
 	}
 	println!("parsed faces lump! ({current_index})");
 	lump_data.push(VBSPLumpType::Faces(faces));
@@ -393,25 +371,29 @@ pub fn parse_vbsp_data_lumps(
 	lump_data.push(VBSPLumpType::None);
 	println!("skipped worldlights lump! ({current_index})");
 
-	// TODO: figure out the structure
 	//      ====LUMP_LEAFFACES====
 	current_index += 1;
 	info = &lump_info[current_index];
 	reader.index = info.file_offset as usize;
 
-	reader.skip(info.length as usize);
-	lump_data.push(VBSPLumpType::None);
-	println!("skipped leaffaces lump! ({current_index})");
+	let mut leaffaces: Vec<u16> = vec![];
+	while reader.index < (info.file_offset + info.length) as usize {
+		leaffaces.push(reader.read_ushort());
+	}
+	lump_data.push(VBSPLumpType::LeafFaces(leaffaces));
+	println!("parsed leaffaces lump! ({current_index})");
 
-	// TODO: figure out the structure
 	//      ====LUMP_LEAFBRUSHES====
 	current_index += 1;
 	info = &lump_info[current_index];
 	reader.index = info.file_offset as usize;
 
-	reader.skip(info.length as usize);
-	lump_data.push(VBSPLumpType::None);
-	println!("skipped leafbrushes lump! ({current_index})");
+	let mut leafbrushes: Vec<u16> = vec![];
+	while reader.index < (info.file_offset + info.length) as usize {
+		leafbrushes.push(reader.read_ushort());
+	}
+	lump_data.push(VBSPLumpType::LeafBrushes(leafbrushes));
+	println!("parsed leafbrushes lump! ({current_index})");
 
 	//      ====LUMP_BRUSHES====
 	current_index += 1;
@@ -535,25 +517,7 @@ pub fn parse_vbsp_data_lumps(
 	// literally the same exact structure as the faces lump
 	let mut orig_faces: Vec<vbsp::Face> = vec![];
 	while reader.index < (info.file_offset + info.length) as usize {
-		orig_faces.push(vbsp::Face {
-			plane_num: reader.read_ushort(),
-			side: reader.read_byte(),
-			on_node: reader.read_byte(),
-			first_edge: reader.read_uint(),
-			num_edges: reader.read_short(),
-			tex_info: reader.read_short(),
-			disp_info: reader.read_short(),
-			surface_fog_volume_id: reader.read_short(),
-			styles: reader.read_int().to_le_bytes(),
-			light_offset: reader.read_int(),
-			area: reader.read_float(),
-			lightmap_texture_mins: [reader.read_int(), reader.read_int()],
-			lightmap_texture_size: [reader.read_int(), reader.read_int()],
-			orig_face: reader.read_int(),
-			num_prims: reader.read_ushort(),
-			first_prim_id: reader.read_ushort(),
-			smoothing_groups: reader.read_uint(),
-		});
+		orig_faces.push(reader.read_face());
 	}
 	println!("parsed originalfaces lump! ({current_index})");
 	lump_data.push(VBSPLumpType::OriginalFaces(orig_faces));
@@ -585,7 +549,7 @@ pub fn parse_vbsp_data_lumps(
 			keydata_size: reader.read_int(),
 			solid_count: reader.read_int(),
 			collision_data: vec![],
-			key_data: vec![],
+			key_data: physcol_data::KeyData { solids: vec![], material_table: vec![], other: vec![] },
 		};
 		if model.model_index == -1 {
 			physmodels.push(model);
@@ -602,30 +566,33 @@ pub fn parse_vbsp_data_lumps(
 				},
 				second_header: ModelHeaders::None,
 				data: vec![],
+				ledges: vec![],
 			};
 			if coll_data.collide_header.model_type == 0 {
 				let surface_size: i32 = reader.read_int();
 				coll_data.second_header = ModelHeaders::CompactSurfaceHeader(
 					physcol_data::CompactSurfaceHeader {
-						surface_size: surface_size.clone(),
+						surface_size,
 						drag_axis_areas: reader.read_vector3(),
 						axis_map_size: reader.read_int(),
 					}
 				);
 				coll_data.data = reader.read_bytes(surface_size as usize);
+				coll_data.ledges = ivp::decode_compact_surface(&coll_data.data)
+				.unwrap_or_default();
 			} else {
 				// it seems theres only model types 1 and 0
 				let size: i32 = reader.read_int();
 				coll_data.second_header = ModelHeaders::MoppSurfaceHeader(
 					physcol_data::MoppSurfaceHeader {
-						size: size.clone(),
+						size,
 					}
 				);
 				coll_data.data = reader.read_bytes(size as usize);
 			}
 			model.collision_data.push(coll_data);
 		}
-		model.key_data = physcol_data::parse_keydata_string(reader.read_string());
+		model.key_data = physcol_data::parse_keydata(&reader.read_string());
 		physmodels.push(model);
 	}
 	lump_data.push(VBSPLumpType::PhysCollide(physmodels));
@@ -741,11 +708,24 @@ pub fn parse_vbsp_data_lumps(
 			leafs: vec![],
 		};
 		for _ in 0..leafs.leaf_entries { leafs.leafs.push(reader.read_ushort()) }
+		let num_entries: i32 = reader.read_int();
+		// the entry size isn't stored anywhere, but the entries fill the rest of the lump
+		let entries_start: usize = reader.index;
+		let entries_end: usize = g_lump_info.file_offset.max(0) as usize + g_lump_info.file_length.max(0) as usize;
+		let stride: usize = if num_entries > 0 { entries_end.saturating_sub(entries_start) / num_entries as usize } else { 0 };
+		let mut prop_types: Vec<u16> = vec![];
+		if stride >= 26 {
+			for i in 0..num_entries as usize {
+				reader.index = entries_start + i * stride + 24;
+				prop_types.push(reader.read_ushort());
+			}
+		}
 		gamelump.data.push(gamelump::GameLumpData::StaticProps(
 			gamelump::StaticProps {
 				dict,
 				leafs,
-				num_entries: reader.read_int(),
+				num_entries,
+				prop_types,
 			}
 		));
 	}
@@ -941,6 +921,94 @@ pub fn parse_vbsp_data_lumps(
 	println!("skipped physcollide lump! ({current_index})");
 
 	// skip ones i havent done yet
+	for i in current_index + 1..51 {
+		lump_data.push(VBSPLumpType::None);
+		println!("skipped lump with index {}!", i);
+	}
+	current_index = 50;
+
+	//      ====LUMP_LEAF_AMBIENT_INDEX_HDR====
+	current_index += 1;
+	info = &lump_info[current_index];
+	reader.index = info.file_offset as usize;
+
+	let mut ambient_indices_hdr: Vec<vbsp::LeafAmbientIndex> = vec![];
+	while reader.index < (info.file_offset + info.length) as usize {
+		ambient_indices_hdr.push(reader.read_leaf_ambient_index());
+	}
+	lump_data.push(VBSPLumpType::LeafAmbientIndexHDR(ambient_indices_hdr));
+	println!("parsed leafambientindexhdr lump! ({current_index})");
+
+	//      ====LUMP_LEAF_AMBIENT_INDEX====
+	current_index += 1;
+	info = &lump_info[current_index];
+	reader.index = info.file_offset as usize;
+
+	let mut ambient_indices: Vec<vbsp::LeafAmbientIndex> = vec![];
+	while reader.index < (info.file_offset + info.length) as usize {
+		ambient_indices.push(reader.read_leaf_ambient_index());
+	}
+	lump_data.push(VBSPLumpType::LeafAmbientIndex(ambient_indices));
+	println!("parsed leafambientindex lump! ({current_index})");
+
+	//      ====LUMP_LIGHTING_HDR====
+	current_index += 1;
+	info = &lump_info[current_index];
+	reader.index = info.file_offset as usize;
+
+	let mut lightings_hdr: Vec<vbsp::ColorRGBExp32> = vec![];
+	while reader.index < (info.file_offset + info.length) as usize {
+		lightings_hdr.push(reader.read_colorrgbexp32());
+	}
+	println!("parsed lighting hdr lump! ({current_index})");
+	lump_data.push(VBSPLumpType::LightingHDR(lightings_hdr));
+
+	//      ====LUMP_WORLDLIGHTS_HDR====
+	current_index += 1;
+	lump_data.push(VBSPLumpType::None);
+	println!("skipped worldlightshdr lump! ({current_index})");
+
+	//      ====LUMP_LEAF_AMBIENT_LIGHTING_HDR====
+	current_index += 1;
+	info = &lump_info[current_index];
+	reader.index = info.file_offset as usize;
+
+	let mut ambient_lighting_hdr: Vec<vbsp::LeafAmbientLighting> = vec![];
+	while reader.index < (info.file_offset + info.length) as usize {
+		ambient_lighting_hdr.push(reader.read_leaf_ambient_lighting());
+	}
+	lump_data.push(VBSPLumpType::LeafAmbientLightingHDR(ambient_lighting_hdr));
+	println!("parsed leafambientlightinghdr lump! ({current_index})");
+
+	//      ====LUMP_LEAF_AMBIENT_LIGHTING====
+	current_index += 1;
+	info = &lump_info[current_index];
+	reader.index = info.file_offset as usize;
+
+	let mut ambient_lighting: Vec<vbsp::LeafAmbientLighting> = vec![];
+	while reader.index < (info.file_offset + info.length) as usize {
+		ambient_lighting.push(reader.read_leaf_ambient_lighting());
+	}
+	lump_data.push(VBSPLumpType::LeafAmbientLighting(ambient_lighting));
+	println!("parsed leafambientlighting lump! ({current_index})");
+
+	//      ====LUMP_XZIPPAKFILE====
+	current_index += 1;
+	lump_data.push(VBSPLumpType::None);
+	println!("skipped xzippakfile lump! ({current_index})");
+
+	//      ====LUMP_FACES_HDR====
+	current_index += 1;
+	info = &lump_info[current_index];
+	reader.index = info.file_offset as usize;
+
+	let mut faces_hdr: Vec<vbsp::Face> = vec![];
+	while reader.index < (info.file_offset + info.length) as usize {
+		faces_hdr.push(reader.read_face());
+	}
+	println!("parsed faceshdr lump! ({current_index})");
+	lump_data.push(VBSPLumpType::FacesHDR(faces_hdr));
+
 	for i in current_index + 1..64 {
 		lump_data.push(VBSPLumpType::None);
 		println!("skipped lump with index {}!", i);
@@ -990,8 +1058,9 @@ pub fn parse_goldsrc_data_lumps(
 	for _ in 0..textures.num_textures { textures.offsets.push(reader.read_int()); }
 	for i in 0..textures.num_textures {
 		// this might cause issues lmao
-		reader.index = (info.file_offset + textures.offsets[i as usize] as u32) as usize;
-		textures.miptexs.push(goldsrc::Miptex {
+		let start: usize = (info.file_offset + textures.offsets[i as usize] as u32) as usize;
+		reader.index = start;
+		let mut miptex: goldsrc::Miptex = goldsrc::Miptex {
 			name: reader.read_sized_string(16),
 			width: reader.read_uint(),
 			height: reader.read_uint(),
@@ -999,7 +1068,12 @@ pub fn parse_goldsrc_data_lumps(
 				reader.read_uint(), reader.read_uint(),
 				reader.read_uint(), reader.read_uint(),
 			],
-		});
+			mips: vec![],
+			palette: vec![],
+		};
+		miptex.mips = read_mips(&reader.bytes[start..], miptex.width, miptex.height, &miptex.offsets);
+		miptex.palette = read_miptex_palette(&reader.bytes[start..], &miptex.offsets, &miptex.mips);
+		textures.miptexs.push(miptex);
 	}
 	lump_data.push(GoldSrcLumpType::Textures(textures));
 	println!("parsed textures lump! ({current_index})");
@@ -1018,8 +1092,12 @@ pub fn parse_goldsrc_data_lumps(
 
 	//      ====LUMP_VISIBILITY====
 	current_index += 1;
-	lump_data.push(GoldSrcLumpType::None);
-	println!("skipped visibility lump! ({current_index})");
+	info = &lump_info[current_index];
+	reader.index = info.file_offset as usize;
+
+	// this can only be decompressed once the leaves and models are known
+	lump_data.push(GoldSrcLumpType::Visibility(reader.read_bytes(info.length as usize)));
+	println!("parsed visibility lump! ({current_index})");
 
 	//      ====LUMP_NODES====
 	current_index += 1;
@@ -1235,8 +1313,9 @@ pub fn parse_quake_data_lumps(
 	}
 	for ofs in &texture.data_offset {
 		if *ofs != -1 {
-			reader.index = info.file_offset as usize + *ofs as usize;
-			texture.miptexs.push(quake::Miptex {
+			let start: usize = info.file_offset as usize + *ofs as usize;
+			reader.index = start;
+			let mut miptex: quake::Miptex = quake::Miptex {
 				name: reader.read_sized_string(16).split('\0').next().unwrap().to_string(),
 				width: reader.read_uint(),
 				height: reader.read_uint(),
@@ -1244,7 +1323,10 @@ pub fn parse_quake_data_lumps(
 					reader.read_uint(), reader.read_uint(),
 					reader.read_uint(), reader.read_uint(),
 				],
-			});
+				mips: vec![],
+			};
+			miptex.mips = read_mips(&reader.bytes[start..], miptex.width, miptex.height, &miptex.offsets);
+			texture.miptexs.push(miptex);
 		}
 	}
 	lump_data.push(QuakeLumpType::Textures(texture));
@@ -1267,8 +1349,9 @@ pub fn parse_quake_data_lumps(
 	info = &lump_info[current_index];
 	reader.index = info.file_offset as usize;
 
-	lump_data.push(QuakeLumpType::Visibility);
-	println!("skipped visibility lump! ({current_index})");
+	// this can only be decompressed once the leaves and models are known
+	lump_data.push(QuakeLumpType::Visibility(reader.read_bytes(info.length as usize)));
+	println!("parsed visibility lump! ({current_index})");
 
 	//      ====LUMP_NODES====
 	current_index += 1;
@@ -1298,10 +1381,14 @@ pub fn parse_quake_data_lumps(
 	while reader.index < (info.file_offset + info.length) as usize {
 		texinfos.push(quake::TexInfo {
 			vecs: [
-				[reader.read_float(), reader.read_float()],
-				[reader.read_float(), reader.read_float()],
-				[reader.read_float(), reader.read_float()],
-				[reader.read_float(), reader.read_float()],
+				[
+					reader.read_float(), reader.read_float(),
+					reader.read_float(), reader.read_float(),
+				],
+				[
+					reader.read_float(), reader.read_float(),
+					reader.read_float(), reader.read_float(),
+				],
 			],
 			miptex: reader.read_int(),
 			flags: reader.read_int(),
@@ -1338,8 +1425,8 @@ pub fn parse_quake_data_lumps(
 	info = &lump_info[current_index];
 	reader.index = info.file_offset as usize;
 
-	lump_data.push(QuakeLumpType::Lighting);
-	println!("skipped lighting lump! ({current_index})");
+	lump_data.push(QuakeLumpType::Lighting(reader.read_bytes(info.length as usize)));
+	println!("parsed lighting lump! ({current_index})");
 
 	//      ====LUMP_CLIPNODES====
 	current_index += 1;
@@ -1439,3 +1526,29 @@ pub fn parse_quake_data_lumps(
 	lump_data.push(QuakeLumpType::Models(models));
 	println!("parsed models lump! ({current_index})");
 }
+
+// reads the 4 mip levels of a miptex, bytes start at the miptex struct.
+// empty if the offsets are zero (texture is in a wad) or point outside of the file
+pub fn read_mips(bytes: &[u8], width: u32, height: u32, offsets: &[u32; 4]) -> Vec<Vec<u8>> {
+	if offsets.contains(&0) { return vec![]; }
+	let mut mips: Vec<Vec<u8>> = vec![];
+	for (level, ofs) in offsets.iter().enumerate() {
+		let size: usize = (width >> level) as usize * (height >> level) as usize;
+		match bytes.get(*ofs as usize..*ofs as usize + size) {
+			Some(data) => mips.push(data.to_vec()),
+			None => return vec![],
+		}
+	}
+	mips
+}
+
+// goldsrc (and wad3) only, a u16 color count (always 256) and then the colors right after the last mip
+pub fn read_miptex_palette(bytes: &[u8], offsets: &[u32; 4], mips: &[Vec<u8>]) -> Vec<[u8; 3]> {
+	let Some(last) = mips.get(3) else { return vec![] };
+	let start: usize = offsets[3] as usize + last.len() + 2;
+	bytes.get(start..start + 256 * 3)
+		.unwrap_or(&[])
+		.chunks(3)
+		.map(|c| [c[0], c[1], c[2]])
+		.collect()
+}
@@ -129,6 +129,50 @@ impl Reader {
 		}
 	}
 
+	// faces, original faces and hdr faces all use this
+	pub fn read_face(
+		&mut self,
+	) -> vbsp::Face {
+		vbsp::Face {
+			plane_num: self.read_ushort(),
+			side: self.read_byte(),
+			on_node: self.read_byte(),
+			first_edge: self.read_uint(),
+			num_edges: self.read_short(),
+			tex_info: self.read_short(),
+			disp_info: self.read_short(),
+			surface_fog_volume_id: self.read_short(),
+			styles: self.read_int().to_le_bytes(), // lmao
+			light_offset: self.read_int(),
+			area: self.read_float(),
+			lightmap_texture_mins: [self.read_int(), self.read_int()],
+			lightmap_texture_size: [self.read_int(), self.read_int()],
+			orig_face: self.read_int(),
+			num_prims: self.read_ushort(),
+			first_prim_id: self.read_ushort(),
+			smoothing_groups: self.read_uint(),
+		}
+	}
+
+	pub fn read_leaf_ambient_index(
+		&mut self,
+	) -> vbsp::LeafAmbientIndex {
+		vbsp::LeafAmbientIndex {
+			ambient_sample_count: self.read_ushort(),
+			first_ambient_sample: self.read_ushort(),
+		}
+	}
+
+	pub fn read_leaf_ambient_lighting(
+		&mut self,
+	) -> vbsp::LeafAmbientLighting {
+		vbsp::LeafAmbientLighting {
+			cube: self.read_compressed_light_cube(),
+			x: self.read_byte(), y: self.read_byte(), z: self.read_byte(),
+			padding: self.read_byte(),
+		}
+	}
+
 	pub fn read_compressed_light_cube(
 		&mut self,
 	) -> vbsp::CompressedLightCube {
@@ -25,11 +25,14 @@ pub enum GameLumpData {
 // sprp
 
 #[derive(Debug, Clone)]
+#[allow(unused)]
 pub struct StaticProps {
 	pub dict: StaticPropDictLump,
 	pub leafs: StaticPropLeafLump,
 	pub num_entries: i32,
 	// pub entries: Vec<StaticPropLump>,
+	// the rest of the entry changes with every version, but the dict index is always at 24
+	pub prop_types: Vec<u16>, // of length num_entries
 }
 
 #[derive(Debug, Clone)]
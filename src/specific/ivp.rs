@@ -0,0 +1,116 @@
+// decoding of the ivp compact surfaces stored in physcollide solids
+// layouts taken from the ivp headers in the leaked 2003 source
+// and from https://developer.valvesoftware.com/wiki/PHY
+// (ivp_compact_surface.hxx, ivp_compact_ledge.hxx)
+
+use crate::{specific::vis::BitSet, utils::Vector3};
+
+// ivp works in meters and has the y axis pointing down
+const METERS_TO_UNITS: f32 = 1.0 / 0.0254;
+
+const LEDGETREE_NODE_SIZE: usize = 28;
+const LEDGE_HEADER_SIZE: usize = 16;
+const TRIANGLE_SIZE: usize = 16;
+const POINT_SIZE: usize = 16; // x, y, z, hesse_val
+
+// a ledge is a single convex piece of the collision model
+#[derive(Debug, Clone)]
+pub struct Ledge {
+	pub points: Vec<Vector3>, // already converted to hammer units
+	pub triangles: Vec<Triangle>,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub struct Triangle {
+	pub material_index: u8,
+	pub is_virtual: bool,
+	pub points: [u16; 3], // indices into the ledge points
+}
+
+fn read_u32(data: &[u8], ofs: usize) -> Option<u32> {
+	Some(u32::from_le_bytes(data.get(ofs..ofs + 4)?.try_into().unwrap()))
+}
+
+fn read_f32(data: &[u8], ofs: usize) -> Option<f32> {
+	Some(f32::from_le_bytes(data.get(ofs..ofs + 4)?.try_into().unwrap()))
+}
+
+// returns none if the data doesnt look like a compact surface
+pub fn decode_compact_surface(data: &[u8]) -> Option<Vec<Ledge>> {
+	// IVP_Compact_Surface
+	//   float mass_center[3], rotation_inertia[3], upper_limit_radius
+	//   uint max_deviation:8, byte_size:24
+	//   int offset_ledgetree_root
+	//   int dummy[3] (last one is "IVPS")
+	if data.get(44..48)? != b"IVPS" { return None; }
+	let root: usize = read_u32(data, 32)? as usize;
+
+	// the ledge tree also has ledges for the convex hulls of inner nodes
+	// so only the ones on terminal nodes are the actual pieces
+	let mut ledges: Vec<Ledge> = vec![];
+	let mut stack: Vec<usize> = vec![root];
+	// the left child is right after its parent, so a right offset of one node size makes both children the same
+	let mut visited: BitSet = BitSet::new(data.len());
+	while let Some(node) = stack.pop() {
+		if visited.get(node) { continue; }
+		visited.set(node);
+		// IVP_Compact_Ledgetree_Node
+		//   int offset_right_node, offset_compact_ledge
+		//   float center[3], radius
+		//   uchar box_sizes[3], free_0
+		let right: i32 = read_u32(data, node)? as i32;
+		let ledge: i32 = read_u32(data, node + 4)? as i32;
+		if right > 0 {
+			stack.push(node + right as usize);
+			stack.push(node + LEDGETREE_NODE_SIZE);
+		} else if ledge != 0 {
+			ledges.push(decode_ledge(data, (node as i64 + ledge as i64) as usize)?);
+		}
+	}
+
+	Some(ledges)
+}
+
+fn decode_ledge(data: &[u8], ofs: usize) -> Option<Ledge> {
+	// IVP_Compact_Ledge
+	//   int c_point_offset (from the start of this ledge)
+	//   int client_data
+	//   uint has_children:2, is_compact:2, dummy:4, size_div_16:24
+	//   short n_triangles, short for_future_use
+	let point_ofs: usize = (ofs as i64 + read_u32(data, ofs)? as i32 as i64) as usize;
+	let n_triangles: usize = (read_u32(data, ofs + 12)? & 0xFFFF) as usize;
+
+	let mut triangles: Vec<Triangle> = vec![];
+	let mut num_points: usize = 0;
+	for t in 0..n_triangles {
+		// IVP_Compact_Triangle
+		//   uint tri_index:12, pierce_index:12, material_index:7, is_virtual:1
+		//   IVP_Compact_Edge edges[3] (start_point_index:16, opposite_index:15, is_virtual:1)
+		let tri_ofs: usize = ofs + LEDGE_HEADER_SIZE + t * TRIANGLE_SIZE;
+		let flags: u32 = read_u32(data, tri_ofs)?;
+		let mut points: [u16; 3] = [0; 3];
+		for (e, point) in points.iter_mut().enumerate() {
+			*point = (read_u32(data, tri_ofs + 4 + e * 4)? & 0xFFFF) as u16;
+			num_points = num_points.max(*point as usize + 1);
+		}
+		triangles.push(Triangle {
+			material_index: ((flags >> 24) & 0x7F) as u8,
+			is_virtual: flags >> 31 != 0,
+			points,
+		});
+	}
+
+	let mut points: Vec<Vector3> = vec![];
+	for p in 0..num_points {
+		let p_ofs: usize = point_ofs + p * POINT_SIZE;
+		let (x, y, z) = (read_f32(data, p_ofs)?, read_f32(data, p_ofs + 4)?, read_f32(data, p_ofs + 8)?);
+		points.push(Vector3 {
+			x: x * METERS_TO_UNITS,
+			y: z * METERS_TO_UNITS,
+			z: -y * METERS_TO_UNITS,
+		});
+	}
+
+	Some(Ledge { points, triangles })
+}
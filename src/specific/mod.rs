@@ -1,5 +1,6 @@
 pub mod cdisp;
 pub mod physcol_data;
+pub mod ivp;
 pub mod occlusion;
 pub mod gamelump;
 pub mod vis;
\ No newline at end of file
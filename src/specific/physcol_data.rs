@@ -1,4 +1,5 @@
 use crate::utils::Vector3;
+use super::ivp;
 
 #[derive(Debug, Clone, Copy)]
 pub struct CollideHeader {
@@ -25,6 +26,7 @@ pub struct CollisionData {
 	pub collide_header: CollideHeader,
 	pub second_header: ModelHeaders,
 	pub data: Vec<u8>, // just bytes
+	pub ledges: Vec<ivp::Ledge>, // decoded convex pieces, empty for mopp surfaces
 }
 
 #[derive(Debug, Clone)]
@@ -34,41 +36,165 @@ pub enum ModelHeaders {
 	MoppSurfaceHeader(MoppSurfaceHeader), // model type 1
 }
 
-// more ugly string parsing code :))))
-pub fn parse_keydata_string(
-	keydata: String,
-) -> Vec<(String, Vec<(String, String)>)> {
-	let objs: Vec<(&str, &str)> = keydata.split("\n}\n")
-	.map(|s| {
-		if s != "\0" {
-			s.split_once(" {\n")
-			.unwrap()
-		} else {
-			("", "")
+#[derive(Debug, Clone)]
+pub struct KeyData {
+	pub solids: Vec<Solid>,
+	pub material_table: Vec<(i32, String)>, // triangle material index -> surfaceprop
+	pub other: Vec<(String, Vec<(String, String)>)>, // fluid, editparams etc
+}
+
+#[derive(Debug, Clone)]
+pub struct Solid {
+	pub index: i32, // which solid of the physmodel this applies to
+	pub mass: f32,
+	pub surfaceprop: String,
+	pub damping: f32,
+	pub rotdamping: f32,
+	pub inertia: f32,
+	pub volume: f32,
+	pub other: Vec<(String, String)>, // anything we dont have a field for
+}
+
+// the keydata is the same text format as the vphysics scripts:
+// name { "key" "value" ... } blocks one after another
+pub fn parse_keydata(keydata: &str) -> KeyData {
+	let mut res: KeyData = KeyData { solids: vec![], material_table: vec![], other: vec![] };
+	let mut tokens = keydata_tokens(keydata).into_iter();
+	while let Some(name) = tokens.next() {
+		if tokens.next().as_deref() != Some("{") { break; }
+		let mut pairs: Vec<(String, String)> = vec![];
+		while let Some(key) = tokens.next() {
+			if key == "}" { break; }
+			match tokens.next() {
+				Some(value) if value != "}" => pairs.push((key, value)),
+				_ => break,
+			}
+		}
+
+		match name.as_str() {
+			"solid" => res.solids.push(solid_from_pairs(pairs)),
+			"materialtable" => for (index, surfaceprop) in pairs {
+				res.material_table.push((index.parse::<i32>().unwrap_or(-1), surfaceprop));
+			},
+			_ => res.other.push((name, pairs)),
+		}
+	}
+	res
+}
+
+fn solid_from_pairs(pairs: Vec<(String, String)>) -> Solid {
+	let mut solid: Solid = Solid {
+		index: -1,
+		mass: 0.0,
+		surfaceprop: String::new(),
+		damping: 0.0,
+		rotdamping: 0.0,
+		inertia: 0.0,
+		volume: 0.0,
+		other: vec![],
+	};
+	for (key, value) in pairs {
+		let float: f32 = value.parse::<f32>().unwrap_or(0.0);
+		match key.as_str() {
+			"index" => solid.index = value.parse::<i32>().unwrap_or(-1),
+			"mass" => solid.mass = float,
+			"surfaceprop" => solid.surfaceprop = value,
+			"damping" => solid.damping = float,
+			"rotdamping" => solid.rotdamping = float,
+			"inertia" => solid.inertia = float,
+			"volume" => solid.volume = float,
+			_ => solid.other.push((key, value)),
 		}
-	})
-	.collect();
+	}
+	solid
+}
 
-	let mut res: Vec<(String, Vec<(String, String)>)> = vec![];
-	for obj in objs {
-		if obj == ("", "") { continue }
-		// this is one of the pieces of code of all time
-		let attrs: Vec<(String, String)> = obj.1.split("\n")
-		.map(|s| {
-			s.split(" ")
-			.map(|s| {
-				s.trim_matches('\"')
-				.to_owned()
-			})
-			.collect::<Vec<String>>()
-		})
-		.map(|vec| {
-			(vec[0].clone(), vec[1].clone())
-		})
-		.collect();
+// quoted strings (which can have spaces), braces and bare words
+fn keydata_tokens(keydata: &str) -> Vec<String> {
+	let mut tokens: Vec<String> = vec![];
+	let mut chars = keydata.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'"' => {
+				let mut token: String = String::new();
+				for c in chars.by_ref() {
+					if c == '"' { break; }
+					token.push(c);
+				}
+				tokens.push(token);
+			},
+			'{' | '}' => tokens.push(c.to_string()),
+			'\0' => break,
+			_ if c.is_whitespace() => {},
+			_ => {
+				let mut token: String = c.to_string();
+				while let Some(c) = chars.peek() {
+					if c.is_whitespace() || matches!(c, '"' | '{' | '}' | '\0') { break; }
+					token.push(*c);
+					chars.next();
+				}
+				tokens.push(token);
+			},
+		}
+	}
+	tokens
+}
 
-		res.push((obj.0.to_owned(), attrs));
+impl KeyData {
+	// surfaceprops used by the solid, the default one first and then the ones
+	// its triangles point to through the material table
+	pub fn solid_surfaceprops(&self, solid: &Solid, data: &CollisionData) -> Vec<String> {
+		let mut res: Vec<String> = vec![solid.surfaceprop.clone()];
+		for index in material_indices(data) {
+			if let Some((_, name)) = self.material_table.iter().find(|(i, _)| *i == index as i32) {
+				if !res.contains(name) { res.push(name.clone()); }
+			}
+		}
+		res
 	}
 
+	// things that dont add up between the keydata and the collision data
+	pub fn problems(&self, collision_data: &[CollisionData]) -> Vec<String> {
+		let mut res: Vec<String> = vec![];
+		for (k, solid) in self.solids.iter().enumerate() {
+			if self.solids[..k].iter().any(|s| s.index == solid.index) {
+				res.push(format!("solid index {} is defined more than once", solid.index));
+			}
+			if solid.surfaceprop.is_empty() {
+				res.push(format!("solid {} has no surfaceprop", solid.index));
+			}
+			let Some(data) = usize::try_from(solid.index).ok().and_then(|i| collision_data.get(i)) else {
+				res.push(format!("solid {} has no collision data", solid.index));
+				continue;
+			};
+			for index in material_indices(data) {
+				if !self.material_table.iter().any(|(i, _)| *i == index as i32) {
+					res.push(format!(
+						"solid {} uses material index {index} which is not in the material table",
+						solid.index,
+					));
+				}
+			}
+		}
+		for i in 0..collision_data.len() {
+			if !self.solids.iter().any(|s| s.index == i as i32) {
+				res.push(format!("collision data {i} has no solid keydata"));
+			}
+		}
+		res
+	}
+}
+
+// material indices other than 0 (which means the solid's own surfaceprop)
+fn material_indices(data: &CollisionData) -> Vec<u8> {
+	let mut res: Vec<u8> = vec![];
+	for ledge in &data.ledges {
+		for tri in &ledge.triangles {
+			if tri.material_index != 0 && !res.contains(&tri.material_index) {
+				res.push(tri.material_index);
+			}
+		}
+	}
+	res.sort();
 	res
 }
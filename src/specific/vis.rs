@@ -1,31 +1,92 @@
 // https://github.com/ValveSoftware/source-sdk-2013/blob/master/sp/src/utils/common/bsplib.cpp#L1417
 // https://www.flipcode.com/archives/Quake_2_BSP_File_Format.shtml <-- i use this
 
-// returns a vec of bools where true means the cluster is visible
-// and false means it isnt
-pub fn decompress_vis(inp: &[u8], num_clusters: &i32) -> Vec<bool> {
-	let mut res: Vec<bool> = vec![false; *num_clusters as usize];
+// one bit per cluster (or leaf), instead of a whole bool for each
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitSet {
+	len: usize,
+	words: Vec<u64>,
+}
+
+impl BitSet {
+	pub fn new(len: usize) -> BitSet {
+		BitSet { len, words: vec![0; len.div_ceil(64)] }
+	}
+
+	pub fn filled(len: usize) -> BitSet {
+		let mut res: BitSet = BitSet::new(len);
+		for i in 0..len { res.set(i); }
+		res
+	}
+
+	// out of range bits are just not set
+	pub fn get(&self, i: usize) -> bool {
+		i < self.len && self.words[i / 64] & (1 << (i % 64)) != 0
+	}
+
+	pub fn set(&mut self, i: usize) {
+		if i < self.len { self.words[i / 64] |= 1 << (i % 64); }
+	}
+
+	pub fn count_ones(&self) -> usize {
+		self.words.iter().map(|w| w.count_ones() as usize).sum()
+	}
+
+	// indices of all the set bits
+	pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+		(0..self.len).filter(|i| self.get(*i))
+	}
+}
+
+// run length decoding, a zero byte is followed by how many zero bytes it stands for
+// and everything else is 8 bits as they are
+// the last byte of a row can have bits past the end, those are padding and get ignored.
+// stops early if the data runs out, everything after that is not visible
+// (source uses this for clusters, quake and goldsrc for leaves)
+pub fn decompress_vis(inp: &[u8], num_bits: usize) -> BitSet {
+	let mut res: BitSet = BitSet::new(num_bits);
 	let mut c: usize = 0;
 	let mut v: usize = 0;
-	while c < *num_clusters as usize {
-		if inp[v] == 0 {
-			v += 1;
-			c += 8 * inp[v] as usize;
-		} else {
-			// TODO: ugly code fix it
-			let mut bit: u8 = 1;
-			while bit != 0 {
-				res[c] = (inp[v] & bit) != 0;
-				bit <<= 1; c += 1;
-				if c >= *num_clusters as usize {
-					// this is an overrun, dunno why it happens
-					// my code is probably wrong somewhere idk
-					// i'll just break for now and figure it out later
-					break;
-				}
-			}
+	while c < num_bits {
+		let Some(byte) = inp.get(v) else { break };
+		if *byte == 0 {
+			let Some(count) = inp.get(v + 1) else { break };
+			c += 8 * *count as usize;
+			v += 2;
+			continue;
 		}
+		for bit in 0..8 {
+			if byte & (1 << bit) != 0 { res.set(c + bit); }
+		}
+		c += 8;
 		v += 1;
 	}
 	res
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn literal_bytes() {
+		let row: BitSet = decompress_vis(&[0b0000_0101, 0b1000_0000], 16);
+		assert_eq!(row.ones().collect::<Vec<usize>>(), vec![0, 2, 15]);
+	}
+
+	#[test]
+	fn zero_runs() {
+		// 0x00 0x02 skips 16 bits
+		let row: BitSet = decompress_vis(&[0x01, 0x00, 0x02, 0x80], 32);
+		assert_eq!(row.ones().collect::<Vec<usize>>(), vec![0, 31]);
+	}
+
+	#[test]
+	fn padding_and_cut_off_data() {
+		// bits past the end of the row are padding
+		assert_eq!(decompress_vis(&[0xFF], 5).count_ones(), 5);
+		// a zero byte without its count stops the row
+		assert_eq!(decompress_vis(&[0x03, 0x00], 24).ones().collect::<Vec<usize>>(), vec![0, 1]);
+		assert_eq!(decompress_vis(&[], 8).count_ones(), 0);
+	}
+}
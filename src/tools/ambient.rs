@@ -0,0 +1,147 @@
+// leaf ambient lighting, the light cubes models get lit with
+// every leaf has a few samples placed inside its bounding box,
+// each one has a color for all 6 axis directions (+x -x +y -y +z -z)
+
+use std::{fs, io::Write};
+use crate::{
+	lumps::vbsp::{self, VBSPLumpType},
+	tools::{lightmap::decode_rgbexp32, vbsp::VBsp},
+	utils::Vector3,
+};
+
+const DIRECTIONS: [&str; 6] = ["pos_x", "neg_x", "pos_y", "neg_y", "pos_z", "neg_z"];
+
+// a sample moved into world space with its colors decoded
+pub struct AmbientSample {
+	pub leaf: usize,
+	pub pos: Vector3,
+	pub cube: [[f32; 3]; 6],
+}
+
+// sample x/y/z are fractions (0-255) of the leaf bounding box
+pub fn sample_position(leaf: &vbsp::Leaf, sample: &vbsp::LeafAmbientLighting) -> Vector3 {
+	let frac = |v: u8, i: usize| {
+		leaf.mins[i] as f32 + (leaf.maxs[i] as f32 - leaf.mins[i] as f32) * v as f32 / 255.0
+	};
+	Vector3 { x: frac(sample.x, 0), y: frac(sample.y, 1), z: frac(sample.z, 2) }
+}
+
+// the index and sample lumps to use, falls back to the other pair if the wanted one is empty
+fn lumps<'a>(bsp: &VBsp<'a>, hdr: bool) -> (&'a [vbsp::LeafAmbientIndex], &'a [vbsp::LeafAmbientLighting]) {
+	let ldr = (bsp.ambient_index, bsp.ambient_lighting);
+	let hdr_lumps = (bsp.ambient_index_hdr, bsp.ambient_lighting_hdr);
+	match hdr {
+		true if !hdr_lumps.1.is_empty() => hdr_lumps,
+		false if !ldr.1.is_empty() => ldr,
+		true => ldr,
+		false => hdr_lumps,
+	}
+}
+
+pub fn leaf_samples(bsp: &VBsp, leaf_index: usize, hdr: bool) -> Vec<AmbientSample> {
+	let Some(leaf) = bsp.leaves.get(leaf_index) else { return vec![] };
+	// version 0 leaves have a single cube in the leaf itself
+	if let Some(cube) = &leaf.ambient_lighting {
+		let center = |i: usize| (leaf.mins[i] as f32 + leaf.maxs[i] as f32) / 2.0;
+		return vec![AmbientSample {
+			leaf: leaf_index,
+			pos: Vector3 { x: center(0), y: center(1), z: center(2) },
+			cube: cube.color.map(|c| decode_rgbexp32(&c)),
+		}];
+	}
+
+	let (indices, samples) = lumps(bsp, hdr);
+	let Some(index) = indices.get(leaf_index) else { return vec![] };
+	let first: usize = index.first_ambient_sample as usize;
+	samples.get(first..first + index.ambient_sample_count as usize)
+	.unwrap_or(&[])
+	.iter()
+	.map(|s| AmbientSample {
+		leaf: leaf_index,
+		pos: sample_position(leaf, s),
+		cube: s.cube.color.map(|c| decode_rgbexp32(&c)),
+	})
+	.collect()
+}
+
+pub fn all_samples(bsp: &VBsp, hdr: bool) -> Vec<AmbientSample> {
+	(0..bsp.leaves.len()).flat_map(|i| leaf_samples(bsp, i, hdr)).collect()
+}
+
+// the ambient cube at a point, blended from the samples of the leaf its in
+// closer samples count more (inverse square distance), like the engine does it
+pub fn cube_at(bsp: &VBsp, point: &Vector3, hdr: bool) -> Option<[[f32; 3]; 6]> {
+	let samples: Vec<AmbientSample> = leaf_samples(bsp, bsp.leaf_at(0, point), hdr);
+	if samples.is_empty() { return None; }
+
+	let mut res: [[f32; 3]; 6] = [[0.0; 3]; 6];
+	let mut total: f32 = 0.0;
+	for sample in &samples {
+		let dist: f32 = (sample.pos - *point).length();
+		let weight: f32 = 1.0 / (dist * dist + 1.0);
+		for (side, color) in res.iter_mut().zip(&sample.cube) {
+			for i in 0..3 { side[i] += color[i] * weight; }
+		}
+		total += weight;
+	}
+	Some(res.map(|side| side.map(|v| v / total)))
+}
+
+pub fn print_cube_at(ld: &[VBSPLumpType], point: Vector3, hdr: bool) {
+	let bsp: VBsp = VBsp::from_lumps(ld);
+	let leaf: usize = bsp.leaf_at(0, &point);
+	println!("point {point} is in leaf {leaf}");
+	match cube_at(&bsp, &point, hdr) {
+		Some(cube) => {
+			for (name, color) in DIRECTIONS.iter().zip(cube) {
+				println!("\t{name}: {}, {}, {}", color[0], color[1], color[2]);
+			}
+		},
+		None => println!("leaf {leaf} has no ambient samples!"),
+	}
+}
+
+// every sample as an ascii .ply point cloud
+// the point color is the average of the 6 sides, the sides themselves are extra float properties
+pub fn export(path: String, ld: &[VBSPLumpType], hdr: bool) {
+	let bsp: VBsp = VBsp::from_lumps(ld);
+	let samples: Vec<AmbientSample> = all_samples(&bsp, hdr);
+	if samples.is_empty() {
+		println!("map has no ambient lighting samples!");
+		return;
+	}
+
+	let mut to_write: String = format!(
+		"ply\nformat ascii 1.0\ncomment ambient samples from bsp_dump {}\nelement vertex {}\n\
+		property float x\nproperty float y\nproperty float z\n\
+		property uchar red\nproperty uchar green\nproperty uchar blue\nproperty int leaf\n",
+		crate::VERSION, samples.len(),
+	);
+	for dir in DIRECTIONS {
+		for channel in ["r", "g", "b"] {
+			to_write.push_str(&format!("property float {dir}_{channel}\n"));
+		}
+	}
+	to_write.push_str("end_header\n");
+
+	for sample in &samples {
+		let average: [u8; 3] = [0, 1, 2].map(|i| {
+			let v: f32 = sample.cube.iter().map(|c| c[i]).sum::<f32>() / 6.0;
+			(v.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0).round() as u8
+		});
+		to_write.push_str(&format!(
+			"{} {} {} {} {} {} {}",
+			sample.pos.x, sample.pos.y, sample.pos.z,
+			average[0], average[1], average[2], sample.leaf,
+		));
+		for color in &sample.cube {
+			to_write.push_str(&format!(" {} {} {}", color[0], color[1], color[2]));
+		}
+		to_write.push('\n');
+	}
+
+	let out_path: String = path.trim_end_matches(".bsp").to_owned() + "-ambient.ply";
+	let mut out: fs::File = fs::File::create(&out_path).unwrap();
+	out.write_all(to_write.as_bytes()).unwrap();
+	println!("exported {} ambient samples to {out_path}", samples.len());
+}
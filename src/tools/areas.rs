@@ -0,0 +1,223 @@
+// the area graph of a source map: areas are connected by area portals,
+// and every portal belongs to a func_areaportal(window) through its portalnumber key.
+// vbsp gives each portal a key and writes it into the entity, areas list the portals
+// they have, so every portal is in the lump twice (once from each side)
+
+use std::{fs, io::Write};
+use crate::{
+	lumps::vbsp::VBSPLumpType,
+	tools::vbsp::VBsp,
+	utils::{entity_value, json_string, Vector3},
+};
+
+pub struct PortalLink {
+	pub key: u16,
+	pub areas: [u16; 2],
+	pub lump_indices: Vec<usize>, // one for each side, should be 2
+	pub plane: i32,
+	pub polygon: Vec<Vector3>,
+	pub entity: Option<usize>,
+}
+
+pub struct AreaGraph {
+	pub num_areas: usize,
+	pub portals: Vec<PortalLink>,
+	pub problems: Vec<String>,
+}
+
+fn is_areaportal(ent: &[(String, String)]) -> bool {
+	matches!(entity_value(ent, "classname"), Some("func_areaportal" | "func_areaportalwindow"))
+}
+
+fn describe(ent: &[(String, String)], index: usize) -> String {
+	format!(
+		"entity {index} ({}{})",
+		entity_value(ent, "classname").unwrap_or(""),
+		entity_value(ent, "targetname").map_or("".to_string(), |t| format!(" \"{t}\"")),
+	)
+}
+
+pub fn build(bsp: &VBsp) -> AreaGraph {
+	let mut portals: Vec<PortalLink> = vec![];
+	let mut problems: Vec<String> = vec![];
+
+	for (area, a) in bsp.areas.iter().enumerate() {
+		let first: usize = a.first_area_portal.max(0) as usize;
+		for index in first..first + a.num_area_portals.max(0) as usize {
+			let Some(portal) = bsp.area_portals.get(index) else {
+				problems.push(format!("area {area} points to portal {index} which doesn't exist"));
+				break;
+			};
+			if let Some(link) = portals.iter_mut().find(|p| p.key == portal.portal_key) {
+				if link.areas != [portal.other_area, area as u16] {
+					problems.push(format!(
+						"portal key {} connects areas {} and {} but is also listed between areas {area} and {}",
+						portal.portal_key, link.areas[0], link.areas[1], portal.other_area,
+					));
+				}
+				link.lump_indices.push(index);
+				continue;
+			}
+
+			let first_vert: usize = portal.first_clip_portal_vert as usize;
+			portals.push(PortalLink {
+				key: portal.portal_key,
+				areas: [area as u16, portal.other_area],
+				lump_indices: vec![index],
+				plane: portal.plane_num,
+				polygon: bsp.clip_portal_verts
+					.get(first_vert..first_vert + portal.clip_portal_verts as usize)
+					.unwrap_or(&[])
+					.iter()
+					.map(|v| v.vec)
+					.collect(),
+				entity: None,
+			});
+		}
+	}
+
+	// hook the entities up to their portals
+	for (i, ent) in bsp.entities.iter().enumerate().filter(|(_, e)| is_areaportal(e)) {
+		let Some(number) = entity_value(ent, "portalnumber") else {
+			problems.push(format!(
+				"{} has no portalnumber, it probably doesn't separate two areas (is it leaking?)",
+				describe(ent, i),
+			));
+			continue;
+		};
+		match portals.iter_mut().find(|p| number.parse::<u16>() == Ok(p.key)) {
+			Some(link) if link.entity.is_some() => problems.push(format!(
+				"{} has portalnumber {number} which already belongs to entity {}",
+				describe(ent, i), link.entity.unwrap(),
+			)),
+			Some(link) => link.entity = Some(i),
+			None => problems.push(format!(
+				"{} has portalnumber {number} but there is no portal with that key",
+				describe(ent, i),
+			)),
+		}
+	}
+
+	for link in &portals {
+		let between: String = format!("portal key {} between areas {} and {}", link.key, link.areas[0], link.areas[1]);
+		if link.lump_indices.len() != 2 {
+			problems.push(format!("{between} is listed {} times instead of once from each side", link.lump_indices.len()));
+		}
+		if link.entity.is_none() {
+			problems.push(format!("{between} has no func_areaportal"));
+		}
+		if link.polygon.len() < 3 {
+			problems.push(format!("{between} has {} clip portal verts", link.polygon.len()));
+		}
+	}
+
+	// area 0 is the solid area outside of the map, everything else should be reachable from area 1
+	let num_areas: usize = bsp.areas.len();
+	if num_areas > 2 {
+		let mut reached: Vec<bool> = vec![false; num_areas];
+		let mut stack: Vec<usize> = vec![1];
+		reached[1] = true;
+		while let Some(area) = stack.pop() {
+			for link in portals.iter().filter(|p| p.areas.contains(&(area as u16))) {
+				let other: usize = (if link.areas[0] as usize == area { link.areas[1] } else { link.areas[0] }) as usize;
+				if other < num_areas && !reached[other] {
+					reached[other] = true;
+					stack.push(other);
+				}
+			}
+		}
+		for (area, reached) in reached.iter().enumerate().skip(1) {
+			if !reached { problems.push(format!("area {area} can't be reached from area 1 through any portal")); }
+		}
+	}
+
+	AreaGraph { num_areas, portals, problems }
+}
+
+fn to_dot(graph: &AreaGraph, bsp: &VBsp) -> String {
+	let mut to_write: String = "graph areas {\n\tnode [shape=box];\n".to_string();
+	for area in 1..graph.num_areas {
+		to_write.push_str(&format!("\tarea{area} [label=\"area {area}\"];\n"));
+	}
+	for link in &graph.portals {
+		let label: String = match link.entity.and_then(|e| bsp.entities.get(e)) {
+			Some(ent) => format!(
+				"portal {}\\n{}",
+				link.key, describe(ent, link.entity.unwrap()).replace('"', "\\\""),
+			),
+			None => format!("portal {}\\nno entity", link.key),
+		};
+		to_write.push_str(&format!(
+			"\tarea{} -- area{} [label=\"{label}\"{}];\n",
+			link.areas[0], link.areas[1],
+			if link.entity.is_none() { ", color=red, style=dashed" } else { "" },
+		));
+	}
+	to_write.push_str("}\n");
+	to_write
+}
+
+fn to_json(graph: &AreaGraph, bsp: &VBsp) -> String {
+	let areas: Vec<String> = (0..graph.num_areas)
+		.map(|area| format!(
+			"{{\"index\":{area},\"portals\":[{}]}}",
+			graph.portals.iter()
+				.filter(|p| p.areas.contains(&(area as u16)))
+				.map(|p| p.key.to_string())
+				.collect::<Vec<String>>()
+				.join(","),
+		))
+		.collect();
+	let portals: Vec<String> = graph.portals.iter()
+		.map(|link| {
+			let entity: String = match link.entity.and_then(|e| bsp.entities.get(e)) {
+				Some(ent) => format!(
+					"{{\"index\":{},\"classname\":{},\"targetname\":{}}}",
+					link.entity.unwrap(),
+					json_string(entity_value(ent, "classname").unwrap_or("")),
+					entity_value(ent, "targetname").map_or("null".to_string(), json_string),
+				),
+				None => "null".to_string(),
+			};
+			format!(
+				"{{\"key\":{},\"areas\":[{},{}],\"lump_indices\":[{}],\"plane\":{},\"polygon\":[{}],\"entity\":{entity}}}",
+				link.key, link.areas[0], link.areas[1],
+				link.lump_indices.iter().map(|i| i.to_string()).collect::<Vec<String>>().join(","),
+				link.plane,
+				link.polygon.iter()
+					.map(|v| format!("[{},{},{}]", v.x, v.y, v.z))
+					.collect::<Vec<String>>()
+					.join(","),
+			)
+		})
+		.collect();
+	format!(
+		"{{\"areas\":[{}],\"portals\":[{}],\"problems\":[{}]}}\n",
+		areas.join(","), portals.join(","),
+		graph.problems.iter().map(|p| json_string(p)).collect::<Vec<String>>().join(","),
+	)
+}
+
+pub fn export(path: String, ld: &[VBSPLumpType], format: &str) {
+	let bsp: VBsp = VBsp::from_lumps(ld);
+	let graph: AreaGraph = build(&bsp);
+	let to_write: String = match format {
+		"dot" => to_dot(&graph, &bsp),
+		"json" => to_json(&graph, &bsp),
+		_ => {
+			println!("unknown area graph format {format}! (expected dot or json)");
+			std::process::exit(0);
+		},
+	};
+
+	let out_path: String = path.trim_end_matches(".bsp").to_owned() + "-areas." + format;
+	let mut out: fs::File = fs::File::create(&out_path).unwrap();
+	out.write_all(to_write.as_bytes()).unwrap();
+	println!(
+		"exported {} areas and {} portals to {out_path}",
+		graph.num_areas.saturating_sub(1), graph.portals.len(),
+	);
+	for problem in &graph.problems {
+		println!("warning: {problem}");
+	}
+}
@@ -0,0 +1,184 @@
+// exports the physcollide lump as actual geometry
+// every ledge (convex piece) becomes its own object so they can be told apart
+
+use std::{fs, io::Write};
+use crate::{
+	lumps::vbsp::{PhysModel, VBSPLumpType},
+	specific::ivp::Ledge,
+	utils::{base64_encode, entity_value, parse_vector, Vector3},
+};
+
+// a convex piece moved into world space
+struct Convex {
+	name: String,
+	points: Vec<Vector3>,
+	triangles: Vec<[u32; 3]>,
+}
+
+pub fn export(
+	path: String,
+	ld: &[VBSPLumpType],
+	format: &str,
+) {
+	let convexes: Vec<Convex> = convexes(ld);
+	let (extension, to_write): (&str, String) = match format {
+		"obj" => ("obj", to_obj(&convexes)),
+		// gltf doesn't allow empty buffers, so there has to be at least one triangle
+		"gltf" if convexes.iter().all(|c| c.points.is_empty() || c.triangles.is_empty()) => {
+			println!("no collision triangles in the map, nothing exported!");
+			return;
+		},
+		"gltf" => ("gltf", to_gltf(&convexes)),
+		_ => {
+			println!("unknown collision format {format}! (expected obj or gltf)");
+			std::process::exit(0);
+		},
+	};
+
+	let out_path: String = path.trim_end_matches(".bsp").to_owned() + "-collision." + extension;
+	let mut out: fs::File = fs::File::create(&out_path).unwrap();
+	out.write_all(to_write.as_bytes()).unwrap();
+	println!("exported collision to {out_path}");
+}
+
+fn convexes(ld: &[VBSPLumpType]) -> Vec<Convex> {
+	let mut res: Vec<Convex> = vec![];
+	let (VBSPLumpType::Entities(entities), VBSPLumpType::PhysCollide(physmodels)) = (&ld[0], &ld[29]) else {
+		return res;
+	};
+
+	for physmodel in physmodels.iter().filter(|m| m.model_index >= 0) {
+		let (origin, angles) = model_transform(entities, physmodel);
+		for (s, solid) in physmodel.collision_data.iter().enumerate() {
+			for (l, ledge) in solid.ledges.iter().enumerate() {
+				res.push(Convex {
+					name: format!("model{}_solid{s}_convex{l}", physmodel.model_index),
+					points: ledge.points.iter()
+						.map(|p| rotate(p, &angles) + origin)
+						.collect(),
+					triangles: ledge_triangles(ledge),
+				});
+			}
+		}
+	}
+	println!(
+		"found {} convexes ({} triangles)",
+		res.len(), res.iter().map(|c| c.triangles.len()).sum::<usize>(),
+	);
+	res
+}
+
+fn ledge_triangles(ledge: &Ledge) -> Vec<[u32; 3]> {
+	ledge.triangles.iter()
+		.map(|t| [t.points[0] as u32, t.points[1] as u32, t.points[2] as u32])
+		.collect()
+}
+
+// brush entity collision is stored relative to the entity
+fn model_transform(entities: &[Vec<(String, String)>], physmodel: &PhysModel) -> (Vector3, Vector3) {
+	if physmodel.model_index == 0 { return (Vector3::new(), Vector3::new()); }
+	let model: String = format!("*{}", physmodel.model_index);
+	let ent: Option<&Vec<(String, String)>> = entities.iter()
+		.find(|e| entity_value(e, "model") == Some(model.as_str()));
+	let get = |key: &str| ent
+		.and_then(|e| entity_value(e, key))
+		.and_then(parse_vector)
+		.unwrap_or(Vector3::new());
+	(get("origin"), get("angles"))
+}
+
+// same as AngleMatrix in the sdk, angles are pitch yaw roll
+fn rotate(p: &Vector3, angles: &Vector3) -> Vector3 {
+	let (sp, cp) = angles.x.to_radians().sin_cos();
+	let (sy, cy) = angles.y.to_radians().sin_cos();
+	let (sr, cr) = angles.z.to_radians().sin_cos();
+	let x_row: Vector3 = Vector3 { x: cp * cy, y: sp * sr * cy - cr * sy, z: sp * cr * cy + sr * sy };
+	let y_row: Vector3 = Vector3 { x: cp * sy, y: sp * sr * sy + cr * cy, z: sp * cr * sy - sr * cy };
+	let z_row: Vector3 = Vector3 { x: -sp, y: sr * cp, z: cr * cp };
+	Vector3 { x: x_row.dot(p), y: y_row.dot(p), z: z_row.dot(p) }
+}
+
+fn to_obj(convexes: &[Convex]) -> String {
+	let mut to_write: String = String::new();
+	let mut first_vertex: u32 = 1;
+	for convex in convexes {
+		to_write.push_str(&format!("o {}\n", convex.name));
+		for p in &convex.points {
+			to_write.push_str(&format!("v {} {} {}\n", p.x, p.y, p.z));
+		}
+		for t in &convex.triangles {
+			to_write.push_str(&format!(
+				"f {} {} {}\n",
+				t[0] + first_vertex, t[1] + first_vertex, t[2] + first_vertex,
+			));
+		}
+		first_vertex += convex.points.len() as u32;
+	}
+	to_write
+}
+
+// a single self contained .gltf with the buffer embedded as a data uri
+// gltf is y up so the source coordinates get rotated
+fn to_gltf(convexes: &[Convex]) -> String {
+	let mut buffer: Vec<u8> = vec![];
+	let mut buffer_views: Vec<String> = vec![];
+	let mut accessors: Vec<String> = vec![];
+	let mut meshes: Vec<String> = vec![];
+	let mut nodes: Vec<String> = vec![];
+
+	// a convex without triangles would need an empty buffer view, which gltf doesn't allow
+	for convex in convexes.iter().filter(|c| !c.points.is_empty() && !c.triangles.is_empty()) {
+		let points: Vec<[f32; 3]> = convex.points.iter().map(|p| [p.x, p.z, -p.y]).collect();
+		let mut mins: [f32; 3] = points[0];
+		let mut maxs: [f32; 3] = points[0];
+		for p in &points {
+			for i in 0..3 {
+				mins[i] = mins[i].min(p[i]);
+				maxs[i] = maxs[i].max(p[i]);
+			}
+		}
+
+		let position_view: usize = buffer_views.len();
+		buffer_views.push(format!(
+			"{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":34962}}",
+			buffer.len(), points.len() * 12,
+		));
+		for p in &points {
+			for f in p { buffer.extend_from_slice(&f.to_le_bytes()); }
+		}
+		let index_view: usize = buffer_views.len();
+		buffer_views.push(format!(
+			"{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":34963}}",
+			buffer.len(), convex.triangles.len() * 12,
+		));
+		for t in &convex.triangles {
+			for i in t { buffer.extend_from_slice(&i.to_le_bytes()); }
+		}
+
+		accessors.push(format!(
+			"{{\"bufferView\":{position_view},\"componentType\":5126,\"count\":{},\"type\":\"VEC3\",\
+			\"min\":[{},{},{}],\"max\":[{},{},{}]}}",
+			points.len(), mins[0], mins[1], mins[2], maxs[0], maxs[1], maxs[2],
+		));
+		accessors.push(format!(
+			"{{\"bufferView\":{index_view},\"componentType\":5125,\"count\":{},\"type\":\"SCALAR\"}}",
+			convex.triangles.len() * 3,
+		));
+		meshes.push(format!(
+			"{{\"name\":\"{}\",\"primitives\":[{{\"attributes\":{{\"POSITION\":{}}},\"indices\":{}}}]}}",
+			convex.name, accessors.len() - 2, accessors.len() - 1,
+		));
+		nodes.push(format!("{{\"name\":\"{}\",\"mesh\":{}}}", convex.name, meshes.len() - 1));
+	}
+
+	format!(
+		"{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"bsp_dump {}\"}},\
+		\"scene\":0,\"scenes\":[{{\"nodes\":[{}]}}],\
+		\"nodes\":[{}],\"meshes\":[{}],\"accessors\":[{}],\"bufferViews\":[{}],\
+		\"buffers\":[{{\"byteLength\":{},\"uri\":\"data:application/octet-stream;base64,{}\"}}]}}\n",
+		crate::VERSION,
+		(0..nodes.len()).map(|i| i.to_string()).collect::<Vec<String>>().join(","),
+		nodes.join(","), meshes.join(","), accessors.join(","), buffer_views.join(","),
+		buffer.len(), base64_encode(&buffer),
+	)
+}
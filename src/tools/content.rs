@@ -0,0 +1,117 @@
+// finding game content (materials, textures, models) for a source map:
+// first in the map's own pakfile, then in a loose game directory.
+// vpks aren't read, so anything that only ships in the game's vpks shows up as missing
+
+use std::{fs, path::{Path, PathBuf}};
+use crate::utils::find_path_ignore_case;
+
+// a file in the pakfile zip, from its central directory
+pub struct PakEntry {
+	pub name: String,
+	pub method: u16, // 0 is stored, bspzip doesn't compress anything unless told to
+	pub compressed_size: usize,
+	pub size: usize,
+	local_header: usize,
+}
+
+pub struct Pak<'a> {
+	bytes: &'a [u8],
+	pub entries: Vec<PakEntry>,
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+	Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().unwrap()))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+	Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().unwrap()))
+}
+
+// lower case with forward slashes, the engine doesn't care about either
+pub fn normalize_path(path: &str) -> String {
+	path.replace('\\', "/").trim_start_matches('/').to_lowercase()
+}
+
+impl Pak<'_> {
+	// an empty pak if the bytes aren't a zip
+	pub fn new(bytes: &[u8]) -> Pak<'_> {
+		let mut pak: Pak = Pak { bytes, entries: vec![] };
+		// the end of central directory record is 22 bytes plus a comment of up to 64k
+		let search_start: usize = bytes.len().saturating_sub(22 + 0xFFFF);
+		let Some(eocd) = (search_start..bytes.len().saturating_sub(21)).rev()
+			.find(|i| bytes[*i..*i + 4] == [0x50, 0x4B, 0x05, 0x06]) else { return pak };
+		let num_entries: usize = read_u16(bytes, eocd + 10).unwrap_or(0) as usize;
+		let mut at: usize = read_u32(bytes, eocd + 16).unwrap_or(0) as usize;
+
+		for _ in 0..num_entries {
+			if read_u32(bytes, at) != Some(0x02014B50) { break; }
+			let (Some(method), Some(compressed_size), Some(size), Some(name_len), Some(extra_len), Some(comment_len), Some(local_header)) = (
+				read_u16(bytes, at + 10), read_u32(bytes, at + 20), read_u32(bytes, at + 24),
+				read_u16(bytes, at + 28), read_u16(bytes, at + 30), read_u16(bytes, at + 32),
+				read_u32(bytes, at + 42),
+			) else { break };
+			let Some(name) = bytes.get(at + 46..at + 46 + name_len as usize) else { break };
+			pak.entries.push(PakEntry {
+				name: String::from_utf8_lossy(name).to_string(),
+				method,
+				compressed_size: compressed_size as usize,
+				size: size as usize,
+				local_header: local_header as usize,
+			});
+			at += 46 + name_len as usize + extra_len as usize + comment_len as usize;
+		}
+		pak
+	}
+
+	pub fn find(&self, path: &str) -> Option<&PakEntry> {
+		let path: String = normalize_path(path);
+		self.entries.iter().find(|e| normalize_path(&e.name) == path)
+	}
+
+	// only stored files can be read, there's no decompression here
+	pub fn read(&self, entry: &PakEntry) -> Result<Vec<u8>, String> {
+		if entry.method != 0 {
+			return Err(format!("{} is compressed (method {})", entry.name, entry.method));
+		}
+		let at: usize = entry.local_header;
+		let (Some(name_len), Some(extra_len)) = (read_u16(self.bytes, at + 26), read_u16(self.bytes, at + 28)) else {
+			return Err(format!("{} has a broken local header", entry.name));
+		};
+		let start: usize = at + 30 + name_len as usize + extra_len as usize;
+		self.bytes.get(start..start + entry.size)
+			.map(|d| d.to_vec())
+			.ok_or(format!("{} goes past the end of the pakfile", entry.name))
+	}
+}
+
+pub enum Location {
+	Pak,
+	Loose(PathBuf),
+}
+
+pub struct Content<'a> {
+	pub pak: Pak<'a>,
+	pub game_dir: Option<PathBuf>,
+}
+
+impl Content<'_> {
+	pub fn new<'a>(pakfile: &'a [u8], game_dir: Option<&str>) -> Content<'a> {
+		Content { pak: Pak::new(pakfile), game_dir: game_dir.map(PathBuf::from) }
+	}
+
+	pub fn find(&self, path: &str) -> Option<Location> {
+		if self.pak.find(path).is_some() { return Some(Location::Pak); }
+		let dir: &Path = self.game_dir.as_deref()?;
+		find_path_ignore_case(dir, path)
+			.filter(|p| p.is_file())
+			.map(Location::Loose)
+	}
+
+	pub fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+		match self.find(path) {
+			Some(Location::Pak) => self.pak.read(self.pak.find(path).unwrap()),
+			Some(Location::Loose(p)) => fs::read(&p).map_err(|e| format!("{}: {e}", p.display())),
+			None => Err(format!("{path} not found")),
+		}
+	}
+}
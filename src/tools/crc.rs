@@ -0,0 +1,88 @@
+// the map crc the engine checks between server and client (CRC_MapFile): a plain crc32 over every lump
+// in index order except the entities, so entity edits don't change it. goldsrc does the same over its 15 lumps,
+// the value is what ends up in svc_serverinfo and goldsrc demo headers
+
+use std::fs;
+use crate::{
+	file_structure::{BSPVersion, Header},
+	image::crc32,
+	parse::parse_header,
+	reader::Reader,
+};
+
+const LUMP_ENTITIES: usize = 0;
+
+// none for quake, the engine never checksums its maps like this
+pub fn map_crc(bytes: &[u8], header: &Header) -> Option<u32> {
+	let num_lumps: usize = match header.bspver {
+		BSPVersion::VBSP => 64,
+		BSPVersion::GoldSrc => 15,
+		_ => return None,
+	};
+	let mut data: Vec<u8> = vec![];
+	for (i, lump) in header.lumps.iter().enumerate().take(num_lumps) {
+		if i == LUMP_ENTITIES { continue; }
+		// the engine reads whatever is there, so a lump past the end of the file is cut off
+		let start: usize = (lump.file_offset as usize).min(bytes.len());
+		let end: usize = (lump.file_offset as usize + lump.length as usize).min(bytes.len());
+		data.extend_from_slice(&bytes[start..end]);
+	}
+	Some(crc32(&data))
+}
+
+pub fn print_crc(path: &str) {
+	let bytes: Vec<u8> = fs::read(path).unwrap_or_else(|e| {
+		println!("error while opening file: {e}");
+		std::process::exit(0);
+	});
+	let mut header: Header = Header::new();
+	parse_header(&mut Reader::new(bytes.clone()), &mut header);
+	match map_crc(&bytes, &header) {
+		// demo parsers and server logs show it as a signed int just as often
+		Some(crc) => println!("map crc: {crc:#010x} ({crc}, signed {})", crc as i32),
+		None => println!("quake doesn't crc its maps (quakeworld uses an md4 checksum instead)"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// entities at 1036, then "abcd" in lump 1, "xyz" in lump 5 and "12345" in lump 40
+	fn test_map(bspver: BSPVersion) -> (Vec<u8>, Header) {
+		let mut bytes: Vec<u8> = vec![0; 1036];
+		let mut header: Header = Header::new();
+		header.bspver = bspver;
+		for (lump, data) in [(0, &b"ENTITIES"[..]), (1, b"abcd"), (5, b"xyz"), (40, b"12345")] {
+			header.lumps[lump].file_offset = bytes.len() as u32;
+			header.lumps[lump].length = data.len() as u32;
+			bytes.extend_from_slice(data);
+		}
+		(bytes, header)
+	}
+
+	#[test]
+	fn crc32_check_value() {
+		// the standard crc32 check value, CRC32_ProcessBuffer in the sdk uses the same table
+		assert_eq!(crc32(b"123456789"), 0xCBF43926);
+		assert_eq!(crc32(b""), 0);
+	}
+
+	#[test]
+	fn map_crc_skips_entities() {
+		// zlib.crc32(b"abcd" + b"xyz" + b"12345")
+		let (mut bytes, header) = test_map(BSPVersion::VBSP);
+		assert_eq!(map_crc(&bytes, &header), Some(0x5AEE5A51));
+		bytes[1036..1044].copy_from_slice(b"entities");
+		assert_eq!(map_crc(&bytes, &header), Some(0x5AEE5A51));
+	}
+
+	#[test]
+	fn map_crc_goldsrc_and_quake() {
+		// only the first 15 lumps count, zlib.crc32(b"abcd" + b"xyz")
+		let (bytes, header) = test_map(BSPVersion::GoldSrc);
+		assert_eq!(map_crc(&bytes, &header), Some(0x8B8838C2));
+		let (bytes, header) = test_map(BSPVersion::Quake);
+		assert_eq!(map_crc(&bytes, &header), None);
+	}
+}
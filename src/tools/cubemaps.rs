@@ -0,0 +1,107 @@
+// checks the cubemaps a source map ships with. vbsp packs patched materials pointing at
+// materials/maps/<map name>/c<x>_<y>_<z>.vtf (and .hdr.vtf) for every env_cubemap, but the vtfs themselves
+// are only written by buildcubemaps. before that there's nothing, or a copy of cubemapdefault.vtf
+// (made from the sky) which has the sky's size instead of the sample's
+
+use std::path::Path;
+use crate::{
+	flags::VtfFlags,
+	lumps::vbsp::{CubemapSample, VBSPLumpType},
+	tools::{content::Pak, vbsp::VBsp, vtf::{format_name, Vtf}},
+};
+
+// what the engine uses for size 0
+const DEFAULT_CUBEMAP_SIZE: u32 = 32;
+
+pub fn resolution(sample: &CubemapSample) -> u32 {
+	if sample.size <= 0 { DEFAULT_CUBEMAP_SIZE } else { 1 << (sample.size - 1).min(31) }
+}
+
+// none if the file is fine, otherwise why it isn't
+fn check_file(pak: &Pak, path: &str, expected: u32, default_bytes: Option<&[u8]>) -> (String, Option<String>) {
+	let Some(entry) = pak.find(path) else { return ("missing".to_string(), Some("missing".to_string())) };
+	let bytes: Vec<u8> = match pak.read(entry) {
+		Ok(bytes) => bytes,
+		Err(e) => return (e.clone(), Some(e)),
+	};
+	let vtf: Vtf = match Vtf::parse(&bytes) {
+		Ok(vtf) => vtf,
+		Err(e) => return (e.clone(), Some(e)),
+	};
+	let info: String = format!("{}x{} {}", vtf.width, vtf.height, format_name(vtf.format));
+	let problem: Option<String> = if default_bytes == Some(&bytes[..]) {
+		Some("copy of cubemapdefault, not built".to_string())
+	} else if !vtf.flags.contains(VtfFlags::EnvMap) {
+		Some("not a cubemap".to_string())
+	} else if vtf.width as u32 != expected || vtf.height as u32 != expected {
+		Some(format!("{}x{} instead of {expected}x{expected}, probably not built", vtf.width, vtf.height))
+	} else {
+		None
+	};
+	(info, problem)
+}
+
+pub fn check(path: String, ld: &[VBSPLumpType]) {
+	let bsp: VBsp = VBsp::from_lumps(ld);
+	let pak: Pak = Pak::new(bsp.pakfile);
+	let map_name: String = Path::new(&path).file_stem().map_or("".to_string(), |s| s.to_string_lossy().to_lowercase());
+	let dir: String = format!("materials/maps/{map_name}");
+
+	// hdr cubemaps are only needed (and only built) when the map has hdr lighting
+	let want_ldr: bool = !bsp.lighting.is_empty() || bsp.lighting_hdr.is_empty();
+	let want_hdr: bool = !bsp.lighting_hdr.is_empty();
+
+	let read_default = |name: &str| pak.find(&format!("{dir}/{name}")).and_then(|e| pak.read(e).ok());
+	let default_ldr: Option<Vec<u8>> = read_default("cubemapdefault.vtf");
+	let default_hdr: Option<Vec<u8>> = read_default("cubemapdefault.hdr.vtf");
+
+	println!(
+		"{} cubemap samples, looking in {dir}/ (expecting {})",
+		bsp.cubemaps.len(),
+		match (want_ldr, want_hdr) { (true, true) => "ldr and hdr", (false, true) => "hdr only", _ => "ldr only" },
+	);
+	let mut problems: Vec<String> = vec![];
+	for (i, sample) in bsp.cubemaps.iter().enumerate() {
+		let [x, y, z] = sample.origin;
+		let expected: u32 = resolution(sample);
+		let name: String = format!("c{x}_{y}_{z}");
+		println!(
+			"\t[cubemap{i}] ({x}, {y}, {z}) size {}{}: {expected}x{expected}",
+			sample.size, if sample.size <= 0 { " (default)" } else { "" },
+		);
+		for (hdr, wanted, default) in [(false, want_ldr, &default_ldr), (true, want_hdr, &default_hdr)] {
+			let file: String = format!("{dir}/{name}{}.vtf", if hdr { ".hdr" } else { "" });
+			let (info, problem) = check_file(&pak, &file, expected, default.as_deref());
+			// an unneeded file that isn't there isn't worth printing
+			if !wanted && pak.find(&file).is_none() { continue; }
+			println!("\t\t{}: {info}{}", if hdr { "hdr" } else { "ldr" }, if wanted { "" } else { " (not needed)" });
+			if let (Some(problem), true) = (problem, wanted) {
+				problems.push(format!("cubemap{i} ({x}, {y}, {z}) {}: {problem}", if hdr { "hdr" } else { "ldr" }));
+			}
+		}
+	}
+
+	// packed cubemaps without a sample, left over from an older compile
+	let mut stray: Vec<&str> = vec![];
+	for entry in &pak.entries {
+		let lower: String = entry.name.to_lowercase();
+		let Some(file) = lower.strip_prefix(&format!("{dir}/")) else { continue };
+		let base: &str = file.trim_end_matches(".vtf").trim_end_matches(".hdr");
+		if !file.ends_with(".vtf") || !base.starts_with('c') || base == "cubemapdefault" { continue; }
+		if !bsp.cubemaps.iter().any(|s| format!("c{}_{}_{}", s.origin[0], s.origin[1], s.origin[2]) == base) {
+			stray.push(&entry.name);
+		}
+	}
+
+	if !stray.is_empty() {
+		println!("\npacked cubemaps without a sample:");
+		for name in &stray { println!("\t{name}"); }
+	}
+	if problems.is_empty() {
+		println!("\nevery cubemap is built");
+	} else {
+		println!("\nunbuilt or broken cubemaps ({}):", problems.len());
+		for problem in &problems { println!("\t{problem}"); }
+		println!("run buildcubemaps in game with the map loaded and repack it");
+	}
+}
@@ -0,0 +1,401 @@
+// rebuilds a .map file out of a compiled goldsrc/quake map
+// there are no brush lumps in these formats so every non-empty leaf of
+// the hull 0 tree is turned back into a convex brush using the planes
+// on the path to it (same idea as bspsource/winbspc)
+// clip brushes only exist in the clipping hulls so those are recovered
+// from the hull 1 clipnodes by shrinking the solid regions back down
+
+use std::{fs, io::Write};
+use crate::{
+	geometry::{self, Plane},
+	specific::vis::BitSet,
+	tools::q1bsp::{self, Q1Bsp, TexInfo},
+	utils::{entity_value, parse_vector, Vector3},
+};
+
+// half of the player box that vis/qbsp expand hull 1 by
+const QUAKE_HULL1: [[f32; 3]; 2] = [[-16.0, -16.0, -24.0], [16.0, 16.0, 32.0]];
+const GOLDSRC_HULL1: [[f32; 3]; 2] = [[-16.0, -16.0, -36.0], [16.0, 16.0, 36.0]];
+
+// the texture axes quake picks for a plane, indexed by the closest axis
+// (normal, s axis, t axis)
+const BASE_AXIS: [[[f32; 3]; 3]; 6] = [
+	[[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]], // floor
+	[[0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]], // ceiling
+	[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]], // west wall
+	[[-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]], // east wall
+	[[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]], // south wall
+	[[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]], // north wall
+];
+
+struct BrushFace {
+	plane: Plane,
+	winding: Vec<Vector3>,
+	texture: String,
+	texinfo: Option<TexInfo>,
+}
+
+pub fn decompile(
+	path: String,
+	bsp: &Q1Bsp,
+) {
+	let out_path: String = path.trim_end_matches(".bsp").to_owned() + "-decompiled.map";
+	let mut out: fs::File = fs::File::create(&out_path).unwrap();
+
+	let mut to_write: String = String::new();
+	let mut brush_count: usize = 0;
+	for (k, ent) in bsp.entities.iter().enumerate() {
+		let model: Option<usize> = if k == 0 {
+			Some(0)
+		} else {
+			entity_value(ent, "model")
+			.and_then(|m| m.strip_prefix('*'))
+			.and_then(|m| m.parse::<usize>().ok())
+		};
+
+		to_write.push_str(&format!("// entity {k}\n{{\n"));
+		for (key, value) in ent {
+			if model.is_some() && key == "model" { continue; }
+			to_write.push_str(&format!("\"{key}\" \"{value}\"\n"));
+		}
+		if k == 0 && bsp.goldsrc && entity_value(ent, "mapversion").is_none() {
+			to_write.push_str("\"mapversion\" \"220\"\n");
+		}
+
+		if let Some(m) = model.filter(|m| *m < bsp.models.len()) {
+			// brush entities with an origin are compiled relative to it
+			let origin: Option<Vector3> = if m != 0 {
+				entity_value(ent, "origin").and_then(parse_vector)
+			} else { None };
+			let offset: Vector3 = origin.unwrap_or(Vector3::new());
+
+			let mut brushes: Vec<Vec<BrushFace>> = model_brushes(bsp, m);
+			brushes.append(&mut clip_brushes(bsp, m));
+			if origin.is_some() && bsp.goldsrc {
+				brushes.push(origin_brush());
+			}
+
+			for brush in &brushes {
+				to_write.push_str(&format!("// brush {brush_count}\n{{\n"));
+				for face in brush {
+					to_write.push_str(&face_string(bsp, face, &offset));
+				}
+				to_write.push_str("}\n");
+				brush_count += 1;
+			}
+		}
+		to_write.push_str("}\n");
+	}
+
+	out.write_all(to_write.as_bytes()).unwrap();
+	println!(
+		"decompiled {} entities and {} brushes to {}",
+		bsp.entities.len(), brush_count, out_path,
+	);
+}
+
+// every non-empty hull 0 leaf of the model as a brush
+fn model_brushes(bsp: &Q1Bsp, model: usize) -> Vec<Vec<BrushFace>> {
+	let mut res: Vec<Vec<BrushFace>> = vec![];
+	let mut stack: Vec<(i32, Vec<Plane>)> = vec![
+		(bsp.models[model].headnodes[0], bounds_planes(bsp, model))
+	];
+	let mut visited: BitSet = BitSet::new(bsp.nodes.len());
+	while let Some((node, planes)) = stack.pop() {
+		if node < 0 {
+			let Some(leaf) = bsp.leaves.get((-1 - node) as usize) else { continue };
+			if leaf.contents == q1bsp::CONTENTS_EMPTY { continue; }
+			let mut faces: Vec<BrushFace> = geometry::brush_windings(&planes)
+			.into_iter()
+			.map(|(plane, winding)| BrushFace {
+				plane, winding, texture: String::new(), texinfo: None,
+			})
+			.collect();
+			if faces.len() < 4 { continue; }
+			texture_brush(bsp, model, leaf.contents, &mut faces);
+			res.push(faces);
+			continue;
+		}
+
+		let Some(n) = bsp.nodes.get(node as usize) else { continue };
+		let Some(&plane) = bsp.planes.get(n.plane) else { continue };
+		if visited.get(node as usize) { continue; }
+		visited.set(node as usize);
+		// front child is in front of the plane, so the brush side faces backwards
+		let mut front: Vec<Plane> = planes.clone();
+		front.push(plane.flipped());
+		let mut back: Vec<Plane> = planes;
+		back.push(plane);
+		stack.push((n.children[1], back));
+		stack.push((n.children[0], front));
+	}
+	res
+}
+
+// solid parts of hull 1 that are empty in hull 0 are clip brushes
+fn clip_brushes(bsp: &Q1Bsp, model: usize) -> Vec<Vec<BrushFace>> {
+	let mut res: Vec<Vec<BrushFace>> = vec![];
+	if bsp.clipnodes.is_empty() || bsp.models[model].headnodes[1] < 0 { return res; }
+
+	let hull: [[f32; 3]; 2] = if bsp.goldsrc { GOLDSRC_HULL1 } else { QUAKE_HULL1 };
+	let mut stack: Vec<(i32, Vec<Plane>)> = vec![
+		(bsp.models[model].headnodes[1], bounds_planes(bsp, model))
+	];
+	let mut visited: BitSet = BitSet::new(bsp.clipnodes.len());
+	while let Some((node, planes)) = stack.pop() {
+		if node < 0 {
+			if node != q1bsp::CONTENTS_SOLID { continue; }
+
+			// the tree splits solid space wherever any plane passes through it,
+			// only the sides that border empty space came from expanding a brush
+			// so those are the only ones that get moved back
+			let shrunk: Vec<Plane> = geometry::brush_windings(&planes)
+			.iter()
+			.map(|(plane, w)| {
+				let across: Vector3 = geometry::winding_center(w) + plane.normal * 0.5;
+				if bsp.hull_contents(model, 1, &across) == q1bsp::CONTENTS_SOLID {
+					*plane
+				} else {
+					shrink_plane(plane, &hull)
+				}
+			})
+			.collect();
+
+			let windings: Vec<(Plane, Vec<Vector3>)> = geometry::brush_windings(&shrunk);
+			if windings.len() < 4 { continue; }
+			let mut center: Vector3 = Vector3::new();
+			for (_, w) in &windings { center = center + geometry::winding_center(w); }
+			center = center * (1.0 / windings.len() as f32);
+			let Some(leaf) = bsp.leaf_at(model, &center) else { continue };
+			if bsp.leaves[leaf].contents != q1bsp::CONTENTS_EMPTY { continue; }
+
+			let texture: &str = if bsp.goldsrc { "CLIP" } else { "clip" };
+			res.push(windings.into_iter()
+				.map(|(plane, winding)| BrushFace {
+					plane, winding, texture: texture.to_string(), texinfo: None,
+				})
+				.collect()
+			);
+			continue;
+		}
+
+		let Some(n) = bsp.clipnodes.get(node as usize) else { continue };
+		let Some(&plane) = bsp.planes.get(n.plane) else { continue };
+		if visited.get(node as usize) { continue; }
+		visited.set(node as usize);
+		let mut front: Vec<Plane> = planes.clone();
+		front.push(plane.flipped());
+		let mut back: Vec<Plane> = planes;
+		back.push(plane);
+		stack.push((n.children[1], back));
+		stack.push((n.children[0], front));
+	}
+	res
+}
+
+// undoes the hull expansion qbsp does for an outward facing plane
+fn shrink_plane(plane: &Plane, hull: &[[f32; 3]; 2]) -> Plane {
+	let n: [f32; 3] = plane.normal.to_array();
+	let mut offset: f32 = 0.0;
+	for i in 0..3 {
+		offset += n[i] * if n[i] > 0.0 { hull[0][i] } else { hull[1][i] };
+	}
+	Plane { normal: plane.normal, dist: plane.dist + offset }
+}
+
+// the model bounding box, closes off leaves that go off into the void
+fn bounds_planes(bsp: &Q1Bsp, model: usize) -> Vec<Plane> {
+	let mins: [f32; 3] = bsp.models[model].mins.to_array();
+	let maxs: [f32; 3] = bsp.models[model].maxs.to_array();
+	let mut res: Vec<Plane> = vec![];
+	for i in 0..3 {
+		let mut normal: [f32; 3] = [0.0; 3];
+		normal[i] = 1.0;
+		res.push(Plane { normal: Vector3::from_array(normal), dist: maxs[i] });
+		normal[i] = -1.0;
+		res.push(Plane { normal: Vector3::from_array(normal), dist: -mins[i] });
+	}
+	res
+}
+
+// 16 unit cube around the model origin, gets moved into place with the rest of the model
+fn origin_brush() -> Vec<BrushFace> {
+	let mut planes: Vec<Plane> = vec![];
+	for i in 0..3 {
+		let mut normal: [f32; 3] = [0.0; 3];
+		normal[i] = 1.0;
+		planes.push(Plane { normal: Vector3::from_array(normal), dist: 8.0 });
+		normal[i] = -1.0;
+		planes.push(Plane { normal: Vector3::from_array(normal), dist: 8.0 });
+	}
+	geometry::brush_windings(&planes)
+	.into_iter()
+	.map(|(plane, winding)| BrushFace {
+		plane, winding, texture: "ORIGIN".to_string(), texinfo: None,
+	})
+	.collect()
+}
+
+// finds the compiled face that lies on each side of the brush to get the texture
+fn texture_brush(bsp: &Q1Bsp, model: usize, contents: i32, faces: &mut [BrushFace]) {
+	let m: &q1bsp::Model = &bsp.models[model];
+	let end: usize = m.first_face.saturating_add(m.num_faces).min(bsp.faces.len());
+	let model_faces: &[q1bsp::Face] = bsp.faces.get(m.first_face..end).unwrap_or(&[]);
+	for face in faces.iter_mut() {
+		let mins: Vector3 = bounds_of(&face.winding, true);
+		let maxs: Vector3 = bounds_of(&face.winding, false);
+		for bsp_face in model_faces {
+			let Some(plane) = bsp.face_plane(bsp_face) else { continue };
+			if !plane.is_same(&face.plane) { continue; }
+			// the compiled face has to actually cover part of this side
+			let verts: Vec<Vector3> = bsp.face_vertices(bsp_face);
+			let (f_mins, f_maxs) = (bounds_of(&verts, true), bounds_of(&verts, false));
+			let overlaps: Vec<f32> = (0..3)
+			.map(|i| {
+				maxs.to_array()[i].min(f_maxs.to_array()[i])
+				- mins.to_array()[i].max(f_mins.to_array()[i])
+			})
+			.collect();
+			if overlaps.iter().any(|o| *o < -1.0) || overlaps.iter().filter(|o| **o > 0.5).count() < 2 {
+				continue;
+			}
+
+			let Some(&texinfo) = bsp.texinfos.get(bsp_face.texinfo) else { continue };
+			face.texture = bsp.texture_name(&texinfo).unwrap_or("").to_string();
+			face.texinfo = Some(texinfo);
+			break;
+		}
+	}
+
+	// hidden sides get whatever texture the rest of the brush uses the most
+	let mut counts: Vec<(String, usize)> = vec![];
+	for face in faces.iter().filter(|f| !f.texture.is_empty()) {
+		match counts.iter_mut().find(|(t, _)| *t == face.texture) {
+			Some((_, c)) => *c += 1,
+			None => counts.push((face.texture.clone(), 1)),
+		}
+	}
+	let fallback: String = counts.iter()
+	.max_by_key(|(_, c)| *c)
+	.map(|(t, _)| t.clone())
+	.unwrap_or_else(|| match (contents, bsp.goldsrc) {
+		(q1bsp::CONTENTS_SKY, true) => "sky".to_string(),
+		(q1bsp::CONTENTS_SKY, false) => "sky1".to_string(),
+		(_, true) => "NULL".to_string(),
+		(_, false) => "skip".to_string(),
+	});
+	for face in faces.iter_mut().filter(|f| f.texture.is_empty()) {
+		face.texture = fallback.clone();
+	}
+}
+
+fn bounds_of(winding: &[Vector3], mins: bool) -> Vector3 {
+	let mut res: Vector3 = winding[0];
+	for p in winding {
+		if mins {
+			res = Vector3 { x: res.x.min(p.x), y: res.y.min(p.y), z: res.z.min(p.z) };
+		} else {
+			res = Vector3 { x: res.x.max(p.x), y: res.y.max(p.y), z: res.z.max(p.z) };
+		}
+	}
+	res
+}
+
+fn face_string(bsp: &Q1Bsp, face: &BrushFace, offset: &Vector3) -> String {
+	// map planes are defined by three points, the normal being (p0 - p1) x (p2 - p1)
+	// models with an origin are compiled around (0, 0, 0)
+	let mut points: [Vector3; 3] = [
+		face.winding[0] + *offset,
+		face.winding[1] + *offset,
+		face.winding[2] + *offset,
+	];
+	let normal: Vector3 = (points[0] - points[1]).cross(&(points[2] - points[1]));
+	if normal.dot(&face.plane.normal) < 0.0 { points.swap(0, 2); }
+
+	let mut res: String = String::new();
+	for p in points {
+		res.push_str(&format!("( {} {} {} ) ", fmt_num(p.x), fmt_num(p.y), fmt_num(p.z)));
+	}
+	res.push_str(&face.texture);
+
+	// texture vectors have to follow the brush when its moved to the origin
+	let texinfo: Option<TexInfo> = face.texinfo.map(|t| TexInfo {
+		s_shift: t.s_shift - t.s.dot(offset),
+		t_shift: t.t_shift - t.t.dot(offset),
+		..t
+	});
+	if bsp.goldsrc {
+		res.push_str(&valve220_texture(face, texinfo));
+	} else {
+		res.push_str(&standard_texture(face, texinfo));
+	}
+	res.push('\n');
+	res
+}
+
+// [ ux uy uz ushift ] [ vx vy vz vshift ] rotation uscale vscale
+fn valve220_texture(face: &BrushFace, texinfo: Option<TexInfo>) -> String {
+	let (s, s_shift, t, t_shift) = match texinfo {
+		Some(ti) => (ti.s, ti.s_shift, ti.t, ti.t_shift),
+		None => {
+			let (xv, yv) = base_axes(&face.plane.normal);
+			(xv, 0.0, yv, 0.0)
+		},
+	};
+	let s_scale: f32 = 1.0 / s.length();
+	let t_scale: f32 = 1.0 / t.length();
+	let s_axis: Vector3 = s.normalized();
+	let t_axis: Vector3 = t.normalized();
+	format!(
+		" [ {} {} {} {} ] [ {} {} {} {} ] 0 {} {}",
+		fmt_num(s_axis.x), fmt_num(s_axis.y), fmt_num(s_axis.z), fmt_num(s_shift),
+		fmt_num(t_axis.x), fmt_num(t_axis.y), fmt_num(t_axis.z), fmt_num(t_shift),
+		fmt_num(s_scale), fmt_num(t_scale),
+	)
+}
+
+// xoffset yoffset rotation xscale yscale
+// this format can only rotate and scale the default axes so its an approximation
+// for textures that were sheared or projected along some other axis
+fn standard_texture(face: &BrushFace, texinfo: Option<TexInfo>) -> String {
+	let Some(ti) = texinfo else { return " 0 0 0 1 1".to_string(); };
+	let (xv, yv) = base_axes(&face.plane.normal);
+	let sv: usize = major_axis(&xv);
+	let tv: usize = major_axis(&yv);
+	let s: [f32; 3] = ti.s.to_array();
+	let t: [f32; 3] = ti.t.to_array();
+
+	// qbsp does s = rotate(xv) / xscale and t = rotate(yv) / yscale,
+	// with xv being +1 on sv and yv being -1 on tv for every base axis
+	let angle: f32 = s[tv].atan2(s[sv]);
+	let (sin, cos) = angle.sin_cos();
+	let x_scale: f32 = 1.0 / (s[sv] * s[sv] + s[tv] * s[tv]).sqrt();
+	let y_scale: f32 = if cos.abs() > sin.abs() { -cos / t[tv] } else { sin / t[sv] };
+
+	format!(
+		" {} {} {} {} {}",
+		fmt_num(ti.s_shift), fmt_num(ti.t_shift), fmt_num(angle.to_degrees()),
+		fmt_num(x_scale), fmt_num(if y_scale.is_finite() { y_scale } else { 1.0 }),
+	)
+}
+
+fn base_axes(normal: &Vector3) -> (Vector3, Vector3) {
+	let mut best: usize = 0;
+	let mut best_dot: f32 = f32::MIN;
+	for (i, axis) in BASE_AXIS.iter().enumerate() {
+		let d: f32 = normal.dot(&Vector3::from_array(axis[0]));
+		if d > best_dot { best_dot = d; best = i; }
+	}
+	(Vector3::from_array(BASE_AXIS[best][1]), Vector3::from_array(BASE_AXIS[best][2]))
+}
+
+fn major_axis(v: &Vector3) -> usize {
+	let a: [f32; 3] = v.to_array();
+	(0..3).max_by(|i, j| a[*i].abs().total_cmp(&a[*j].abs())).unwrap()
+}
+
+// rounds off float noise so the file stays readable
+fn fmt_num(v: f32) -> String {
+	let rounded: f32 = (v * 1000.0).round() / 1000.0;
+	if rounded == 0.0 { "0".to_string() } else { rounded.to_string() }
+}
@@ -0,0 +1,219 @@
+// decodes face lightmaps and packs them into atlas images
+// every (face, style, bump) lightmap gets its own spot in the atlas
+// and a text file says where each one ended up
+
+use std::{fs, io::Write};
+use crate::{
+	flags::SurfaceFlags,
+	image::{write_hdr, write_png},
+	lumps::vbsp::{self, VBSPLumpType},
+	tools::{q1bsp::Q1Bsp, vbsp::VBsp},
+};
+
+const PAGE_SIZE: u32 = 1024;
+const PADDING: u32 = 1;
+
+// a single lightmap to be placed in the atlas, in linear color
+pub struct LightmapImage {
+	pub label: String,
+	pub width: u32,
+	pub height: u32,
+	pub pixels: Vec<[f32; 3]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AtlasFormat {
+	Png,
+	Hdr,
+}
+
+pub fn export_vbsp(
+	path: String,
+	ld: &[VBSPLumpType],
+	format: AtlasFormat,
+) {
+	let bsp: VBsp = VBsp::from_lumps(ld);
+	// ldr pngs come from the ldr lump and .hdr from the hdr one,
+	// but either works if thats all the map has
+	let lighting: &[vbsp::ColorRGBExp32] = match format {
+		AtlasFormat::Png if !bsp.lighting.is_empty() => bsp.lighting,
+		AtlasFormat::Hdr if !bsp.lighting_hdr.is_empty() => bsp.lighting_hdr,
+		_ if !bsp.lighting.is_empty() => bsp.lighting,
+		_ => bsp.lighting_hdr,
+	};
+	if lighting.is_empty() {
+		println!("map has no lighting!");
+		return;
+	}
+	println!(
+		"using the {} lighting lump",
+		if std::ptr::eq(lighting, bsp.lighting) { "ldr" } else { "hdr" },
+	);
+
+	let faces: &[vbsp::Face] = if std::ptr::eq(lighting, bsp.lighting) { bsp.faces } else { bsp.hdr_faces() };
+	let mut images: Vec<LightmapImage> = vec![];
+	for (i, face) in faces.iter().enumerate() {
+		images.append(&mut vbsp_face_lightmaps(&bsp, lighting, i, face));
+	}
+	write_atlases(&path, &images, format, tonemap);
+}
+
+pub fn export_q1(
+	path: String,
+	bsp: &Q1Bsp,
+	format: AtlasFormat,
+) {
+	if bsp.lighting.is_empty() {
+		println!("map has no lighting!");
+		return;
+	}
+
+	let mut images: Vec<LightmapImage> = vec![];
+	for (i, face) in bsp.faces.iter().enumerate() {
+		let Some((_, size)) = bsp.lightmap_extents(face) else { continue };
+		for (style, luxels) in bsp.face_lightmaps(face) {
+			images.push(LightmapImage {
+				label: format!("face {i} style {style}"),
+				width: size[0],
+				height: size[1],
+				pixels: luxels.iter().map(|c| c.map(|v| v as f32 / 255.0)).collect(),
+			});
+		}
+	}
+	// these are already stored the way they get displayed
+	write_atlases(&path, &images, format, |c| c.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8));
+}
+
+// all the lightmaps of a face, style by style and bump by bump
+// (bumpmapped faces have the flat one and then one for each of the 3 bump basis vectors)
+pub fn vbsp_face_lightmaps(
+	bsp: &VBsp,
+	lighting: &[vbsp::ColorRGBExp32],
+	index: usize,
+	face: &vbsp::Face,
+) -> Vec<LightmapImage> {
+	let mut res: Vec<LightmapImage> = vec![];
+	if face.light_offset < 0 { return res; }
+	let Some(texinfo) = bsp.texinfos.get(face.tex_info as usize) else { return res };
+
+	let width: u32 = (face.lightmap_texture_size[0] + 1).max(0) as u32;
+	let height: u32 = (face.lightmap_texture_size[1] + 1).max(0) as u32;
+	let luxels: usize = width as usize * height as usize;
+	let bumps: usize = if texinfo.flags.contains(SurfaceFlags::BumpLight) { 4 } else { 1 };
+	let styles: usize = face.styles.iter().take_while(|s| **s != 255).count();
+
+	let start: usize = face.light_offset as usize / 4;
+	for style in 0..styles {
+		for bump in 0..bumps {
+			let first: usize = start + (style * bumps + bump) * luxels;
+			let Some(samples) = lighting.get(first..first + luxels) else { return res };
+			res.push(LightmapImage {
+				label: format!("face {index} style {} bump {bump}", face.styles[style]),
+				width,
+				height,
+				pixels: samples.iter().map(decode_rgbexp32).collect(),
+			});
+		}
+	}
+	res
+}
+
+pub fn decode_rgbexp32(c: &vbsp::ColorRGBExp32) -> [f32; 3] {
+	let scale: f32 = 2f32.powi(c.exponent as i32) / 255.0;
+	[c.r as f32 * scale, c.g as f32 * scale, c.b as f32 * scale]
+}
+
+// clamps and applies a 2.2 gamma, close to what the engine shows
+fn tonemap(c: [f32; 3]) -> [u8; 3] {
+	c.map(|v| (v.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0).round() as u8)
+}
+
+// (page, x, y)
+type Placement = (usize, u32, u32);
+
+// simple shelf packing, tallest first
+// returns where every image goes and the size of every page
+fn pack(images: &[LightmapImage]) -> (Vec<Placement>, Vec<(u32, u32)>) {
+	let mut order: Vec<usize> = (0..images.len()).collect();
+	order.sort_by(|a, b| images[*b].height.cmp(&images[*a].height));
+
+	let mut places: Vec<Placement> = vec![(0, 0, 0); images.len()];
+	let mut pages: Vec<(u32, u32)> = vec![];
+	let (mut x, mut y, mut shelf): (u32, u32, u32) = (0, 0, 0);
+	for i in order {
+		let w: u32 = images[i].width + PADDING;
+		let h: u32 = images[i].height + PADDING;
+		if pages.is_empty() { pages.push((0, 0)); }
+		if x + w > PAGE_SIZE && x > 0 {
+			x = 0;
+			y += shelf;
+			shelf = 0;
+		}
+		if y + h > PAGE_SIZE && y > 0 {
+			pages.push((0, 0));
+			(x, y, shelf) = (0, 0, 0);
+		}
+
+		let page: usize = pages.len() - 1;
+		places[i] = (page, x, y);
+		pages[page].0 = pages[page].0.max(x + images[i].width);
+		pages[page].1 = pages[page].1.max(y + images[i].height);
+		x += w;
+		shelf = shelf.max(h);
+	}
+	(places, pages)
+}
+
+pub fn write_atlases(
+	path: &str,
+	images: &[LightmapImage],
+	format: AtlasFormat,
+	tonemap: fn([f32; 3]) -> [u8; 3],
+) {
+	let base: String = path.trim_end_matches(".bsp").to_owned();
+	let (places, pages) = pack(images);
+
+	let mut index: String = String::new();
+	for (page, (width, height)) in pages.iter().enumerate() {
+		let mut pixels: Vec<[f32; 3]> = vec![[0.0; 3]; *width as usize * *height as usize];
+		let mut used: Vec<bool> = vec![false; pixels.len()];
+		for (image, (p, x, y)) in images.iter().zip(&places) {
+			if *p != page { continue; }
+			index.push_str(&format!(
+				"{}: page {page} at ({x}, {y}) size {}x{}\n",
+				image.label, image.width, image.height,
+			));
+			for row in 0..image.height {
+				for col in 0..image.width {
+					let dst: usize = ((y + row) * width + x + col) as usize;
+					pixels[dst] = image.pixels[(row * image.width + col) as usize];
+					used[dst] = true;
+				}
+			}
+		}
+
+		let out_path: String = match format {
+			AtlasFormat::Png => {
+				let mut rgba: Vec<u8> = Vec::with_capacity(pixels.len() * 4);
+				for (pixel, used) in pixels.iter().zip(&used) {
+					rgba.extend_from_slice(&tonemap(*pixel));
+					rgba.push(if *used { 255 } else { 0 });
+				}
+				let out_path: String = format!("{base}-lightmap{page}.png");
+				write_png(&out_path, *width, *height, &rgba);
+				out_path
+			},
+			AtlasFormat::Hdr => {
+				let out_path: String = format!("{base}-lightmap{page}.hdr");
+				write_hdr(&out_path, *width, *height, &pixels);
+				out_path
+			},
+		};
+		println!("wrote {out_path} ({width}x{height})");
+	}
+
+	let index_path: String = format!("{base}-lightmaps.txt");
+	let mut out: fs::File = fs::File::create(&index_path).unwrap();
+	out.write_all(index.as_bytes()).unwrap();
+	println!("wrote {} lightmaps, see {index_path} for where each one is", images.len());
+}
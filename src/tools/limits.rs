@@ -0,0 +1,253 @@
+// how close a map is to the engine's hard limits (the MAX_MAP_* defines in bspfile.h), like vbspinfo prints.
+// element counts come from the lump sizes, so they're right even for lumps that aren't parsed
+
+use crate::{
+	file_structure::{BSPFile, BSPVersion, Header},
+	lumps::lumptype::Lumps,
+	tools::{q1bsp::Q1Bsp, vbsp::VBsp},
+};
+
+// fullness that gets a warning
+const WARN_PERCENT: f64 = 90.0;
+
+// size 0 means max is in bytes and not elements
+struct Limit {
+	name: &'static str,
+	lump: usize,
+	size: usize,
+	max: usize,
+}
+
+const fn limit(name: &'static str, lump: usize, size: usize, max: usize) -> Limit {
+	Limit { name, lump, size, max }
+}
+
+// source 2013 (bsp versions 19 and 20)
+const SOURCE_LIMITS: [Limit; 33] = [
+	limit("models", 14, 48, 1024),
+	limit("brushes", 18, 12, 8192),
+	limit("brushsides", 19, 8, 65536),
+	limit("planes", 1, 20, 65536),
+	limit("vertexes", 3, 12, 65536),
+	limit("nodes", 5, 32, 65536),
+	limit("texinfos", 6, 72, 12288),
+	limit("texdata", 2, 32, 2048),
+	limit("dispinfos", 26, 176, 2048),
+	limit("disp_verts", 33, 20, 2048 * 17 * 17),
+	limit("disp_tris", 48, 2, 2048 * 16 * 16 * 2),
+	limit("faces", 7, 56, 65536),
+	limit("hdr faces", 58, 56, 65536),
+	limit("origfaces", 27, 56, 65536),
+	limit("leaffaces", 16, 2, 65536),
+	limit("leafbrushes", 17, 2, 65536),
+	limit("areas", 20, 8, 256),
+	limit("areaportals", 21, 12, 1024),
+	limit("clipportalverts", 41, 12, 128000),
+	limit("edges", 12, 4, 256000),
+	limit("surfedges", 13, 4, 512000),
+	limit("vertnormals", 30, 12, 256000),
+	limit("vertnormalindices", 31, 2, 256000),
+	limit("worldlights", 15, 88, 8192),
+	limit("cubemapsamples", 42, 16, 1024),
+	limit("overlays", 45, 352, 512),
+	limit("primitives", 37, 10, 32768),
+	limit("primverts", 38, 12, 65536),
+	limit("primindices", 39, 2, 65536),
+	limit("texdata strings", 44, 4, 65536),
+	limit("texdata string data", 43, 0, 256000),
+	limit("lightdata", 8, 0, 0x1000000),
+	limit("visdata", 4, 0, 0x1000000),
+];
+
+// zhlt's bspfile.h, the engine agrees on all of these
+const GOLDSRC_LIMITS: [Limit; 15] = [
+	limit("models", 14, 64, 400),
+	limit("planes", 1, 20, 32767),
+	limit("vertexes", 3, 12, 65535),
+	limit("nodes", 5, 24, 32767),
+	limit("texinfos", 6, 40, 8192),
+	limit("faces", 7, 20, 65535),
+	limit("clipnodes", 9, 8, 32767),
+	limit("leaves", 10, 28, 8192),
+	limit("marksurfaces", 11, 2, 65535),
+	limit("edges", 12, 4, 256000),
+	limit("surfedges", 13, 4, 512000),
+	limit("texdata", 2, 0, 0x200000),
+	limit("lightdata", 8, 0, 0x200000),
+	limit("visdata", 4, 0, 0x200000),
+	limit("entdata", 0, 0, 128 * 1024),
+];
+
+// id's qbsp, same structs as goldsrc but mostly smaller
+const QUAKE_LIMITS: [Limit; 15] = [
+	limit("models", 14, 64, 256),
+	limit("planes", 1, 20, 32767),
+	limit("vertexes", 3, 12, 65535),
+	limit("nodes", 5, 24, 32767),
+	limit("texinfos", 6, 40, 4096),
+	limit("faces", 7, 20, 65535),
+	limit("clipnodes", 9, 8, 32767),
+	limit("leaves", 10, 28, 8192),
+	limit("marksurfaces", 11, 2, 65535),
+	limit("edges", 12, 4, 256000),
+	limit("surfedges", 13, 4, 512000),
+	limit("texdata", 2, 0, 0x200000),
+	limit("lightdata", 8, 0, 0x100000),
+	limit("visdata", 4, 0, 0x100000),
+	limit("entdata", 0, 0, 65536),
+];
+
+struct Usage {
+	name: String,
+	count: Option<usize>, // none for the byte limits
+	max: usize,
+	bytes: usize,
+	misaligned: bool, // lump length isn't a multiple of the struct size
+}
+
+impl Usage {
+	fn percent(&self) -> f64 {
+		self.count.unwrap_or(self.bytes) as f64 / self.max.max(1) as f64 * 100.0
+	}
+}
+
+fn from_table(file: &BSPFile, table: &[Limit]) -> Vec<Usage> {
+	table.iter()
+		.map(|l| {
+			let bytes: usize = file.header.lumps[l.lump].length as usize;
+			Usage {
+				name: l.name.to_string(),
+				count: bytes.checked_div(l.size),
+				max: l.max,
+				bytes,
+				misaligned: l.size > 0 && !bytes.is_multiple_of(l.size),
+			}
+		})
+		.collect()
+}
+
+// leaves lost their ambient lighting cube in lump version 1
+fn leaf_size(header: &Header) -> usize {
+	if header.lumps[10].version == 0 { 56 } else { 32 }
+}
+
+// name, lump index and struct size of every lump that has an element limit
+pub fn struct_sizes(header: &Header) -> Vec<(&'static str, usize, usize)> {
+	let table: &[Limit] = match header.bspver {
+		BSPVersion::VBSP => &SOURCE_LIMITS,
+		BSPVersion::GoldSrc => &GOLDSRC_LIMITS,
+		_ => &QUAKE_LIMITS,
+	};
+	let mut res: Vec<(&'static str, usize, usize)> = table.iter()
+		.filter(|l| l.size > 0)
+		.map(|l| (l.name, l.lump, l.size))
+		.collect();
+	if let BSPVersion::VBSP = header.bspver { res.push(("leaves", 10, leaf_size(header))); }
+	res
+}
+
+fn vbsp_usage(file: &BSPFile, bsp: &VBsp) -> Vec<Usage> {
+	let mut res: Vec<Usage> = from_table(file, &SOURCE_LIMITS);
+	let version: i32 = file.header.version;
+	let lump = |i: usize| file.header.lumps[i];
+
+	let leaf_size: usize = leaf_size(&file.header);
+	res.push(Usage {
+		name: "leaves".to_string(),
+		count: Some(lump(10).length as usize / leaf_size),
+		max: 65536,
+		bytes: lump(10).length as usize,
+		misaligned: !(lump(10).length as usize).is_multiple_of(leaf_size),
+	});
+	// left 4 dead 2 and later (version 21+) doubled the entity limit
+	res.push(Usage {
+		name: "entities".to_string(),
+		count: Some(bsp.entities.len()),
+		max: if version >= 21 { 16384 } else { 8192 },
+		bytes: lump(0).length as usize,
+		misaligned: false,
+	});
+	res.push(Usage {
+		name: "entdata".to_string(),
+		count: None,
+		max: 256 * 1024,
+		bytes: lump(0).length as usize,
+		misaligned: false,
+	});
+	if let Some(vis) = bsp.vis {
+		res.push(Usage {
+			name: "clusters".to_string(),
+			count: Some(vis.num_clusters.max(0) as usize),
+			max: 65536,
+			bytes: 0,
+			misaligned: false,
+		});
+	}
+	res
+}
+
+fn q1_usage(file: &BSPFile, bsp: &Q1Bsp) -> Vec<Usage> {
+	let table: &[Limit] = match file.header.bspver {
+		BSPVersion::GoldSrc => &GOLDSRC_LIMITS,
+		_ => &QUAKE_LIMITS,
+	};
+	let mut res: Vec<Usage> = from_table(file, table);
+	res.push(Usage {
+		name: "entities".to_string(),
+		count: Some(bsp.entities.len()),
+		max: 1024,
+		bytes: file.header.lumps[0].length as usize,
+		misaligned: false,
+	});
+	res.push(Usage {
+		name: "textures".to_string(),
+		count: Some(bsp.miptexs.len()),
+		max: 512,
+		bytes: file.header.lumps[2].length as usize,
+		misaligned: false,
+	});
+	res
+}
+
+pub fn report(file: &BSPFile) {
+	let usage: Vec<Usage> = match &file.lump_data {
+		Lumps::VBSP(ld) => vbsp_usage(file, &VBsp::from_lumps(ld)),
+		_ => q1_usage(file, &Q1Bsp::from_file(file).unwrap()),
+	};
+
+	match file.header.bspver {
+		BSPVersion::VBSP => println!("source bsp version {}, map revision {}\n", file.header.version, file.header.map_revision),
+		BSPVersion::GoldSrc => println!("goldsrc bsp (version 30)\n"),
+		_ => println!("quake bsp (version 29)\n"),
+	}
+	println!("{:<22} {:>9} / {:<16} {:>11}  fullness", "object", "count", "max", "bytes");
+	println!("{:<22} {:>9}   {:<16} {:>11}  --------", "------", "-----", "---", "-----");
+	let mut warnings: Vec<String> = vec![];
+	for u in &usage {
+		let percent: f64 = u.percent();
+		let (count, max): (String, String) = match u.count {
+			Some(count) => (count.to_string(), u.max.to_string()),
+			None => ("".to_string(), format!("{} bytes", u.max)),
+		};
+		println!(
+			"{:<22} {count:>9} {} {max:<16} {:>11}  {percent:>6.1}%{}",
+			u.name, if u.count.is_some() { "/" } else { " " }, u.bytes,
+			if percent > 100.0 { "  OVER" } else if percent >= WARN_PERCENT { "  near limit" } else { "" },
+		);
+		if percent > 100.0 {
+			warnings.push(format!("{} is over the limit ({percent:.1}%), the engine won't load this map", u.name));
+		} else if percent >= WARN_PERCENT {
+			warnings.push(format!("{} is at {percent:.1}% of the limit", u.name));
+		}
+		if u.misaligned {
+			warnings.push(format!("{} lump is {} bytes, which isn't a whole number of elements", u.name, u.bytes));
+		}
+	}
+
+	if warnings.is_empty() {
+		println!("\nno limits close to being hit");
+	} else {
+		println!("\nwarnings:");
+		for warning in &warnings { println!("\t{warning}"); }
+	}
+}
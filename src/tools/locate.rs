@@ -0,0 +1,59 @@
+// finds the leaf (and its contents, cluster and area) a point is in
+// by walking the world bsp tree
+
+use crate::{
+	file_structure::BSPFile,
+	lumps::{lumptype::Lumps, vbsp::VBSPLumpType},
+	tools::{q1bsp::{self, Q1Bsp}, vbsp::VBsp},
+	utils::{bitflags_to_string, Vector3},
+};
+
+pub fn locate(file: &BSPFile, point: Vector3) {
+	println!("point {point}");
+	match &file.lump_data {
+		Lumps::VBSP(ld) => locate_vbsp(ld, &point),
+		_ => locate_q1(&Q1Bsp::from_file(file).unwrap(), &point),
+	}
+}
+
+fn locate_vbsp(ld: &[VBSPLumpType], point: &Vector3) {
+	let bsp: VBsp = VBsp::from_lumps(ld);
+	let leaf: usize = bsp.leaf_at(0, point);
+	let Some(l) = bsp.leaves.get(leaf) else {
+		println!("map has no leaves!");
+		return;
+	};
+	println!("\tleaf: {leaf}");
+	println!("\tcontents: {}", bitflags_to_string(l.contents.iter_names()));
+	// -1 is outside of the map (or in a solid leaf)
+	println!("\tcluster: {}", l.cluster);
+	println!("\tarea: {}", bsp.leaf_area(leaf).unwrap_or(0));
+}
+
+fn locate_q1(bsp: &Q1Bsp, point: &Vector3) {
+	let Some(leaf) = bsp.leaf_at(0, point) else {
+		println!("map has no leaves!");
+		return;
+	};
+	let l: &q1bsp::Leaf = &bsp.leaves[leaf];
+	println!("\tleaf: {leaf}");
+	println!("\tcontents: {}", q1bsp::contents_name(l.contents));
+	// there are no clusters here, every visleaf is its own one (leaf 0 isnt one)
+	println!(
+		"\tcluster: {}",
+		if leaf == 0 || leaf > bsp.num_visleafs() { -1 } else { leaf as i32 - 1 },
+	);
+	// and there are no areas either, so the clipping hulls go here instead
+	// (quake only has two of them, hull 3 is goldsrc's crouching hull)
+	let hulls: &[(usize, &str)] = if bsp.goldsrc {
+		&[(1, "player"), (2, "large"), (3, "crouching player")]
+	} else {
+		&[(1, "player"), (2, "large")]
+	};
+	for &(hull, name) in hulls {
+		println!(
+			"\thull {hull} ({name}): {}",
+			q1bsp::contents_name(bsp.hull_contents(0, hull, point)),
+		);
+	}
+}
@@ -0,0 +1,210 @@
+// checks that every material a source map uses can be found, in the pakfile or in a game directory.
+// materials come from the texdata strings (brushes, displacements, overlays) and from the static prop models,
+// every vmt that is found is parsed for its shader and the textures it points to
+
+use crate::{
+	lumps::vbsp::VBSPLumpType,
+	tools::{content::{normalize_path, Content, Location}, mdl::Mdl, vbsp::VBsp},
+};
+
+// vmt parameters that point to a vtf, there's a lot more but these are the ones every shader has
+const TEXTURE_PARAMS: [&str; 8] = [
+	"$basetexture", "$basetexture2", "$bumpmap", "$bumpmap2",
+	"$normalmap", "$envmapmask", "$detail", "$selfillummask",
+];
+
+// a flattened vmt, every key (lower case) with its value no matter how deep it is
+pub struct Vmt {
+	pub shader: String,
+	pub params: Vec<(String, String)>,
+}
+
+impl Vmt {
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self.params.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+	}
+}
+
+// keyvalues tokens, quoted or not, with // comments skipped
+fn tokenize(text: &str) -> Vec<String> {
+	let mut tokens: Vec<String> = vec![];
+	let mut chars = text.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'{' | '}' => tokens.push(c.to_string()),
+			'"' => tokens.push(chars.by_ref().take_while(|c| *c != '"').collect()),
+			'/' if chars.peek() == Some(&'/') => { for c in chars.by_ref() { if c == '\n' { break; } } },
+			c if c.is_whitespace() => {},
+			c => {
+				let mut token: String = c.to_string();
+				while let Some(next) = chars.peek() {
+					if next.is_whitespace() || matches!(next, '{' | '}' | '"') { break; }
+					token.push(chars.next().unwrap());
+				}
+				tokens.push(token);
+			},
+		}
+	}
+	tokens
+}
+
+pub fn parse_vmt(text: &str) -> Option<Vmt> {
+	let tokens: Vec<String> = tokenize(text);
+	let mut iter = tokens.into_iter();
+	let shader: String = iter.next()?;
+	if iter.next()? != "{" { return None; }
+
+	let mut params: Vec<(String, String)> = vec![];
+	let mut depth: usize = 1;
+	while let Some(key) = iter.next() {
+		match key.as_str() {
+			"}" => {
+				depth -= 1;
+				if depth == 0 { break; }
+			},
+			"{" => depth += 1,
+			_ => match iter.next()? {
+				// a block like proxies or a patch's insert, its keys end up in the same list
+				v if v == "{" => depth += 1,
+				v => params.push((key.to_lowercase(), v)),
+			},
+		}
+	}
+	Some(Vmt { shader, params })
+}
+
+struct MaterialUse {
+	name: String,
+	users: Vec<String>,
+}
+
+fn add_use(uses: &mut Vec<MaterialUse>, name: &str, user: String) {
+	let name: String = normalize_path(name.trim_end_matches(".vmt"));
+	match uses.iter_mut().find(|u| u.name == name) {
+		Some(u) => { if !u.users.contains(&user) { u.users.push(user); } },
+		None => uses.push(MaterialUse { name, users: vec![user] }),
+	}
+}
+
+// patches that include patches only get followed this deep, so ones that include themselves end
+const MAX_PATCH_DEPTH: usize = 8;
+
+// reads a vmt and follows patch materials to the one they include
+fn load_vmt(content: &Content, path: &str, depth: usize) -> Result<Vmt, String> {
+	if depth > MAX_PATCH_DEPTH {
+		return Err(format!("patch materials nested more than {MAX_PATCH_DEPTH} deep (does one include itself?)"));
+	}
+	let bytes: Vec<u8> = content.read(path)?;
+	let vmt: Vmt = parse_vmt(&String::from_utf8_lossy(&bytes)).ok_or("couldn't parse the vmt".to_string())?;
+	if !vmt.shader.eq_ignore_ascii_case("patch") { return Ok(vmt); }
+
+	let include: String = vmt.get("include").ok_or("patch material without an include".to_string())?.to_string();
+	// only the first include is named, a loop would repeat it for every level
+	let base: Vmt = load_vmt(content, &include, depth + 1)
+		.map_err(|e| if depth == 0 { format!("patched material {include}: {e}") } else { e })?;
+	// the patch's own keys win
+	let mut params: Vec<(String, String)> = vmt.params;
+	params.extend(base.params);
+	Ok(Vmt { shader: base.shader, params })
+}
+
+pub fn check(ld: &[VBSPLumpType], game_dir: Option<&str>) {
+	let bsp: VBsp = VBsp::from_lumps(ld);
+	let content: Content = Content::new(bsp.pakfile, game_dir);
+	let mut uses: Vec<MaterialUse> = vec![];
+
+	let mut face_counts: Vec<usize> = vec![0; bsp.texdata.len()];
+	for face in bsp.faces {
+		let Some(texinfo) = bsp.texinfos.get(face.tex_info as usize) else { continue };
+		if let Some(count) = face_counts.get_mut(texinfo.texdata as usize) { *count += 1; }
+	}
+	for (texdata, count) in face_counts.iter().enumerate().filter(|(_, c)| **c > 0) {
+		if let Some(name) = bsp.texdata_name(texdata) { add_use(&mut uses, name, format!("{count} faces")); }
+	}
+	for overlay in bsp.overlays {
+		let texdata: Option<usize> = bsp.texinfos.get(overlay.texinfo as usize).map(|t| t.texdata as usize);
+		if let Some(name) = texdata.and_then(|t| bsp.texdata_name(t)) {
+			add_use(&mut uses, name, format!("overlay {}", overlay.id));
+		}
+	}
+	// anything else in the string data (vbsp only writes what it uses, but still)
+	for string in bsp.texdata_strings {
+		let name: &str = string.val.trim_end_matches('\0');
+		if !uses.iter().any(|u| u.name == normalize_path(name)) {
+			add_use(&mut uses, name, "texdata only".to_string());
+		}
+	}
+
+	let mut problems: Vec<String> = vec![];
+	for model in bsp.static_props.map_or(&[] as &[String], |p| &p.dict.names) {
+		let mdl: Mdl = match content.read(model).and_then(|b| Mdl::parse(&b)) {
+			Ok(mdl) => mdl,
+			Err(e) => {
+				problems.push(format!("static prop model {model}: {e}, its materials weren't checked"));
+				continue;
+			},
+		};
+		// the first cdmaterials directory that has the material is the one the engine uses
+		for candidates in mdl.material_candidates() {
+			let found: Option<&String> = candidates.iter().find(|c| content.find(&format!("materials/{c}.vmt")).is_some());
+			if let Some(name) = found.or(candidates.first()) {
+				add_use(&mut uses, name, format!("static prop {model}"));
+			}
+		}
+	}
+
+	println!("{} materials referenced", uses.len());
+	let (mut missing, mut found): (Vec<String>, usize) = (vec![], 0);
+	let mut missing_textures: Vec<String> = vec![];
+	for u in &uses {
+		let path: String = format!("materials/{}.vmt", u.name);
+		let Some(location) = content.find(&path) else {
+			missing.push(format!("\t{} (used by {})", u.name, u.users.join(", ")));
+			continue;
+		};
+		found += 1;
+		let vmt: Vmt = match load_vmt(&content, &path, 0) {
+			Ok(vmt) => vmt,
+			Err(e) => {
+				println!("\t[{}] {}: {e}", short_location(&location), u.name);
+				problems.push(format!("material {}: {e}", u.name));
+				continue;
+			},
+		};
+		println!(
+			"\t[{}] {}: {}{}",
+			short_location(&location), u.name, vmt.shader,
+			vmt.get("$basetexture").map_or("".to_string(), |t| format!(", $basetexture {t}")),
+		);
+		for param in TEXTURE_PARAMS {
+			let Some(texture) = vmt.get(param) else { continue };
+			// render targets and the cubemap placeholder aren't files
+			if texture.starts_with("_rt_") || texture.eq_ignore_ascii_case("env_cubemap") { continue; }
+			if content.find(&format!("materials/{}.vtf", texture.trim_end_matches(".vtf"))).is_none() {
+				missing_textures.push(format!("\t{texture} ({param} of {})", u.name));
+			}
+		}
+	}
+
+	println!("\nmissing materials ({}):", missing.len());
+	for line in &missing { println!("{line}"); }
+	println!("\nmissing textures ({}):", missing_textures.len());
+	for line in &missing_textures { println!("{line}"); }
+	if !problems.is_empty() {
+		println!("\nother problems:");
+		for problem in &problems { println!("\t{problem}"); }
+	}
+	println!(
+		"\n{found} of {} materials found{}, {} missing, {} missing textures",
+		uses.len(),
+		if content.game_dir.is_some() { "" } else { " (only the pakfile was searched)" },
+		missing.len(), missing_textures.len(),
+	);
+}
+
+fn short_location(location: &Location) -> &str {
+	match location {
+		Location::Pak => "pak",
+		Location::Loose(_) => "game",
+	}
+}
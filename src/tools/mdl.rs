@@ -0,0 +1,85 @@
+// just enough of the studiomdl .mdl header (studiohdr_t) to know which materials a model uses
+// and if the model can be a static prop
+use crate::flags::MdlFlags;
+
+// "IDST"
+const MDL_MAGIC: u32 = 0x54534449;
+
+pub struct Mdl {
+	pub version: i32,
+	// the vvd and vtx files have to have the same one or the model doesn't load
+	pub checksum: i32,
+	pub flags: MdlFlags,
+	// texture names, looked up in every cdmaterials directory in order
+	pub textures: Vec<String>,
+	pub cd_materials: Vec<String>,
+}
+
+fn read_i32(bytes: &[u8], at: usize) -> Option<i32> {
+	Some(i32::from_le_bytes(bytes.get(at..at + 4)?.try_into().unwrap()))
+}
+
+// null terminated string at an offset
+fn read_string(bytes: &[u8], at: usize) -> Option<String> {
+	let rest: &[u8] = bytes.get(at..)?;
+	let end: usize = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+	Some(String::from_utf8_lossy(&rest[..end]).to_string())
+}
+
+impl Mdl {
+	pub fn parse(bytes: &[u8]) -> Result<Mdl, String> {
+		if read_i32(bytes, 0).map(|m| m as u32) != Some(MDL_MAGIC) {
+			return Err("not an mdl file".to_string());
+		}
+		let header = |at: usize| read_i32(bytes, at).ok_or("header is cut off".to_string());
+		let mut mdl: Mdl = Mdl {
+			version: header(4)?,
+			checksum: header(8)?,
+			flags: MdlFlags::from_bits_retain(header(152)? as u32),
+			textures: vec![],
+			cd_materials: vec![],
+		};
+
+		// mstudiotexture_t is 64 bytes, the name offset is relative to the struct
+		let (num_textures, texture_index): (i32, i32) = (header(204)?, header(208)?);
+		for i in 0..num_textures.max(0) as usize {
+			// broken offsets can be negative or huge, so none of this is allowed to overflow
+			let name: Option<String> = usize::try_from(texture_index).ok()
+				.and_then(|index| index.checked_add(i * 64))
+				.and_then(|at| at.checked_add_signed(read_i32(bytes, at)? as isize))
+				.and_then(|at| read_string(bytes, at));
+			mdl.textures.push(name.ok_or(format!("texture {i} is outside of the file"))?);
+		}
+
+		// offsets from the start of the file this time
+		let (num_cd, cd_index): (i32, i32) = (header(212)?, header(216)?);
+		for i in 0..num_cd.max(0) as usize {
+			let name: Option<String> = usize::try_from(cd_index).ok()
+				.and_then(|index| index.checked_add(i * 4))
+				.and_then(|at| read_i32(bytes, at))
+				.and_then(|ofs| read_string(bytes, usize::try_from(ofs).ok()?));
+			mdl.cd_materials.push(name.ok_or(format!("cdmaterials {i} is outside of the file"))?);
+		}
+		Ok(mdl)
+	}
+
+	// every material path the model could use, per texture in cdmaterials order (what the engine tries)
+	pub fn material_candidates(&self) -> Vec<Vec<String>> {
+		self.textures.iter()
+			.map(|tex| self.cd_materials.iter()
+				.map(|dir| format!("{}/{}", dir.replace('\\', "/").trim_end_matches('/'), tex.replace('\\', "/")))
+				.collect())
+			.collect()
+	}
+
+	pub fn is_static_prop(&self) -> bool {
+		self.flags.contains(MdlFlags::StaticProp)
+	}
+}
+
+// the checksum in a model's .vvd (vertexFileHeader_t) or .vtx (FileHeader_t)
+pub fn vertex_file_checksum(bytes: &[u8], vtx: bool) -> Option<i32> {
+	if vtx { return read_i32(bytes, 16); }
+	if bytes.get(0..4) != Some(b"IDSV") { return None; }
+	read_i32(bytes, 8)
+}
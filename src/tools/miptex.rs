@@ -0,0 +1,130 @@
+// turns the textures embedded in goldsrc and quake maps into pngs.
+// goldsrc textures carry their own palette, quake ones use the game's palette.lmp
+// (the standard one is built in below)
+
+use std::{fs, path::Path};
+use crate::{
+	image::write_png,
+	tools::{q1bsp::{Miptex, Q1Bsp}, wad::{self, TextureReport, TextureSource}},
+};
+
+// gfx/palette.lmp from quake's pak0.pak
+pub const QUAKE_PALETTE: [[u8; 3]; 256] = [
+	[0x00, 0x00, 0x00], [0x0f, 0x0f, 0x0f], [0x1f, 0x1f, 0x1f], [0x2f, 0x2f, 0x2f], [0x3f, 0x3f, 0x3f], [0x4b, 0x4b, 0x4b], [0x5b, 0x5b, 0x5b], [0x6b, 0x6b, 0x6b],
+	[0x7b, 0x7b, 0x7b], [0x8b, 0x8b, 0x8b], [0x9b, 0x9b, 0x9b], [0xab, 0xab, 0xab], [0xbb, 0xbb, 0xbb], [0xcb, 0xcb, 0xcb], [0xdb, 0xdb, 0xdb], [0xeb, 0xeb, 0xeb],
+	[0x0f, 0x0b, 0x07], [0x17, 0x0f, 0x0b], [0x1f, 0x17, 0x0b], [0x27, 0x1b, 0x0f], [0x2f, 0x23, 0x13], [0x37, 0x2b, 0x17], [0x3f, 0x2f, 0x17], [0x4b, 0x37, 0x1b],
+	[0x53, 0x3b, 0x1b], [0x5b, 0x43, 0x1f], [0x63, 0x4b, 0x1f], [0x6b, 0x53, 0x1f], [0x73, 0x57, 0x1f], [0x7b, 0x5f, 0x23], [0x83, 0x67, 0x23], [0x8f, 0x6f, 0x23],
+	[0x0b, 0x0b, 0x0f], [0x13, 0x13, 0x1b], [0x1b, 0x1b, 0x27], [0x27, 0x27, 0x33], [0x2f, 0x2f, 0x3f], [0x37, 0x37, 0x4b], [0x3f, 0x3f, 0x57], [0x47, 0x47, 0x67],
+	[0x4f, 0x4f, 0x73], [0x5b, 0x5b, 0x7f], [0x63, 0x63, 0x8b], [0x6b, 0x6b, 0x97], [0x73, 0x73, 0xa3], [0x7b, 0x7b, 0xaf], [0x83, 0x83, 0xbb], [0x8b, 0x8b, 0xcb],
+	[0x00, 0x00, 0x00], [0x07, 0x07, 0x00], [0x0b, 0x0b, 0x00], [0x13, 0x13, 0x00], [0x1b, 0x1b, 0x00], [0x23, 0x23, 0x00], [0x2b, 0x2b, 0x07], [0x2f, 0x2f, 0x07],
+	[0x37, 0x37, 0x07], [0x3f, 0x3f, 0x07], [0x47, 0x47, 0x07], [0x4b, 0x4b, 0x0b], [0x53, 0x53, 0x0b], [0x5b, 0x5b, 0x0b], [0x63, 0x63, 0x0b], [0x6b, 0x6b, 0x0f],
+	[0x07, 0x00, 0x00], [0x0f, 0x00, 0x00], [0x17, 0x00, 0x00], [0x1f, 0x00, 0x00], [0x27, 0x00, 0x00], [0x2f, 0x00, 0x00], [0x37, 0x00, 0x00], [0x3f, 0x00, 0x00],
+	[0x47, 0x00, 0x00], [0x4f, 0x00, 0x00], [0x57, 0x00, 0x00], [0x5f, 0x00, 0x00], [0x67, 0x00, 0x00], [0x6f, 0x00, 0x00], [0x77, 0x00, 0x00], [0x7f, 0x00, 0x00],
+	[0x13, 0x13, 0x00], [0x1b, 0x1b, 0x00], [0x23, 0x23, 0x00], [0x2f, 0x2b, 0x00], [0x37, 0x2f, 0x00], [0x43, 0x37, 0x00], [0x4b, 0x3b, 0x07], [0x57, 0x43, 0x07],
+	[0x5f, 0x47, 0x07], [0x6b, 0x4b, 0x0b], [0x77, 0x53, 0x0f], [0x83, 0x57, 0x13], [0x8b, 0x5b, 0x13], [0x97, 0x5f, 0x1b], [0xa3, 0x63, 0x1f], [0xaf, 0x67, 0x23],
+	[0x23, 0x13, 0x07], [0x2f, 0x17, 0x0b], [0x3b, 0x1f, 0x0f], [0x4b, 0x23, 0x13], [0x57, 0x2b, 0x17], [0x63, 0x2f, 0x1f], [0x73, 0x37, 0x23], [0x7f, 0x3b, 0x2b],
+	[0x8f, 0x43, 0x33], [0x9f, 0x4f, 0x33], [0xaf, 0x63, 0x2f], [0xbf, 0x77, 0x2f], [0xcf, 0x8f, 0x2b], [0xdf, 0xab, 0x27], [0xef, 0xcb, 0x1f], [0xff, 0xf3, 0x1b],
+	[0x0b, 0x07, 0x00], [0x1b, 0x13, 0x00], [0x2b, 0x23, 0x0f], [0x37, 0x2b, 0x13], [0x47, 0x33, 0x1b], [0x53, 0x37, 0x23], [0x63, 0x3f, 0x2b], [0x6f, 0x47, 0x33],
+	[0x7f, 0x53, 0x3f], [0x8b, 0x5f, 0x47], [0x9b, 0x6b, 0x53], [0xa7, 0x7b, 0x5f], [0xb7, 0x87, 0x6b], [0xc3, 0x93, 0x7b], [0xd3, 0xa3, 0x8b], [0xe3, 0xb3, 0x97],
+	[0xab, 0x8b, 0xa3], [0x9f, 0x7f, 0x97], [0x93, 0x73, 0x87], [0x8b, 0x67, 0x7b], [0x7f, 0x5b, 0x6f], [0x77, 0x53, 0x63], [0x6b, 0x4b, 0x57], [0x5f, 0x3f, 0x4b],
+	[0x57, 0x37, 0x43], [0x4b, 0x2f, 0x37], [0x43, 0x27, 0x2f], [0x37, 0x1f, 0x23], [0x2b, 0x17, 0x1b], [0x23, 0x13, 0x13], [0x17, 0x0b, 0x0b], [0x0f, 0x07, 0x07],
+	[0xbb, 0x73, 0x9f], [0xaf, 0x6b, 0x8f], [0xa3, 0x5f, 0x83], [0x97, 0x57, 0x77], [0x8b, 0x4f, 0x6b], [0x7f, 0x4b, 0x5f], [0x73, 0x43, 0x53], [0x6b, 0x3b, 0x4b],
+	[0x5f, 0x33, 0x3f], [0x53, 0x2b, 0x37], [0x47, 0x23, 0x2b], [0x3b, 0x1f, 0x23], [0x2f, 0x17, 0x1b], [0x23, 0x13, 0x13], [0x17, 0x0b, 0x0b], [0x0f, 0x07, 0x07],
+	[0xdb, 0xc3, 0xbb], [0xcb, 0xb3, 0xa7], [0xbf, 0xa3, 0x9b], [0xaf, 0x97, 0x8b], [0xa3, 0x87, 0x7b], [0x97, 0x7b, 0x6f], [0x87, 0x6f, 0x5f], [0x7b, 0x63, 0x53],
+	[0x6b, 0x57, 0x47], [0x5f, 0x4b, 0x3b], [0x53, 0x3f, 0x33], [0x43, 0x33, 0x27], [0x37, 0x2b, 0x1f], [0x27, 0x1f, 0x17], [0x1b, 0x13, 0x0f], [0x0f, 0x0b, 0x07],
+	[0x6f, 0x83, 0x7b], [0x67, 0x7b, 0x6f], [0x5f, 0x73, 0x67], [0x57, 0x6b, 0x5f], [0x4f, 0x63, 0x57], [0x47, 0x5b, 0x4f], [0x3f, 0x53, 0x47], [0x37, 0x4b, 0x3f],
+	[0x2f, 0x43, 0x37], [0x2b, 0x3b, 0x2f], [0x23, 0x33, 0x27], [0x1f, 0x2b, 0x1f], [0x17, 0x23, 0x17], [0x0f, 0x1b, 0x13], [0x0b, 0x13, 0x0b], [0x07, 0x0b, 0x07],
+	[0xff, 0xf3, 0x1b], [0xef, 0xdf, 0x17], [0xdb, 0xcb, 0x13], [0xcb, 0xb7, 0x0f], [0xbb, 0xa7, 0x0f], [0xab, 0x97, 0x0b], [0x9b, 0x83, 0x07], [0x8b, 0x73, 0x07],
+	[0x7b, 0x63, 0x07], [0x6b, 0x53, 0x00], [0x5b, 0x47, 0x00], [0x4b, 0x37, 0x00], [0x3b, 0x2b, 0x00], [0x2b, 0x1f, 0x00], [0x1b, 0x0f, 0x00], [0x0b, 0x07, 0x00],
+	[0x00, 0x00, 0xff], [0x0b, 0x0b, 0xef], [0x13, 0x13, 0xdf], [0x1b, 0x1b, 0xcf], [0x23, 0x23, 0xbf], [0x2b, 0x2b, 0xaf], [0x2f, 0x2f, 0x9f], [0x2f, 0x2f, 0x8f],
+	[0x2f, 0x2f, 0x7f], [0x2f, 0x2f, 0x6f], [0x2f, 0x2f, 0x5f], [0x2b, 0x2b, 0x4f], [0x23, 0x23, 0x3f], [0x1b, 0x1b, 0x2f], [0x13, 0x13, 0x1f], [0x0b, 0x0b, 0x0f],
+	[0x2b, 0x00, 0x00], [0x3b, 0x00, 0x00], [0x4b, 0x07, 0x00], [0x5f, 0x07, 0x00], [0x6f, 0x0f, 0x00], [0x7f, 0x17, 0x07], [0x93, 0x1f, 0x07], [0xa3, 0x27, 0x0b],
+	[0xb7, 0x33, 0x0f], [0xc3, 0x4b, 0x1b], [0xcf, 0x63, 0x2b], [0xdb, 0x7f, 0x3b], [0xe3, 0x97, 0x4f], [0xe7, 0xab, 0x5f], [0xef, 0xbf, 0x77], [0xf7, 0xd3, 0x8b],
+	[0xa7, 0x7b, 0x3b], [0xb7, 0x9b, 0x37], [0xc7, 0xc3, 0x37], [0xe7, 0xe3, 0x57], [0x7f, 0xbf, 0xff], [0xab, 0xe7, 0xff], [0xd7, 0xff, 0xff], [0x67, 0x00, 0x00],
+	[0x8b, 0x00, 0x00], [0xb3, 0x00, 0x00], [0xd7, 0x00, 0x00], [0xff, 0x00, 0x00], [0xff, 0xf3, 0x93], [0xff, 0xf7, 0xc7], [0xff, 0xff, 0xff], [0x9f, 0x5b, 0x53],
+];
+
+// palette.lmp is just the 256 colors, nothing else
+pub fn read_palette(path: &str) -> Vec<[u8; 3]> {
+	let bytes: Vec<u8> = fs::read(path).unwrap_or_else(|e| {
+		println!("error while opening palette: {e}");
+		std::process::exit(0);
+	});
+	if bytes.len() < 256 * 3 {
+		println!("{path} is too small to be a palette ({} bytes, expected 768)", bytes.len());
+		std::process::exit(0);
+	}
+	bytes.chunks(3).take(256).map(|c| [c[0], c[1], c[2]]).collect()
+}
+
+// rgba pixels of one mip level, none if the texture isnt embedded.
+// textures starting with { are transparent where they use the last color
+pub fn decode(miptex: &Miptex, level: usize, palette: &[[u8; 3]]) -> Option<Vec<u8>> {
+	let indices: &Vec<u8> = miptex.mips.get(level)?;
+	let transparent: bool = miptex.name.starts_with('{');
+	let mut rgba: Vec<u8> = Vec::with_capacity(indices.len() * 4);
+	for index in indices {
+		let color: [u8; 3] = palette.get(*index as usize).copied().unwrap_or([0, 0, 0]);
+		rgba.extend_from_slice(&color);
+		rgba.push(if transparent && *index == 255 { 0 } else { 255 });
+	}
+	Some(rgba)
+}
+
+// * (liquids) isnt allowed in file names on windows, most tools use # instead
+fn file_name(name: &str) -> String {
+	name.chars()
+		.map(|c| match c {
+			'*' => '#',
+			'/' | '\\' | ':' | '?' | '"' | '<' | '>' | '|' => '_',
+			_ => c,
+		})
+		.collect()
+}
+
+// writes the top mip of every embedded texture to file-textures/name.png,
+// with a game directory the ones from wads too
+pub fn export(path: String, bsp: &Q1Bsp, game_dir: Option<&str>, palette_path: Option<&str>) {
+	let palette: Vec<[u8; 3]> = match palette_path {
+		Some(p) => read_palette(p),
+		None => QUAKE_PALETTE.to_vec(),
+	};
+	let report: TextureReport = wad::resolve(bsp, game_dir.map(Path::new));
+	for (name, wad) in &report.wads {
+		if let Err(e) = wad {
+			println!("couldn't load wad {name}: {e}");
+		}
+	}
+
+	let out_dir: String = path.trim_end_matches(".bsp").to_owned() + "-textures";
+	fs::create_dir_all(&out_dir).unwrap();
+
+	let mut exported: usize = 0;
+	let mut missing: Vec<&str> = vec![];
+	for (miptex, source) in bsp.miptexs.iter().zip(&report.sources) {
+		if miptex.name.is_empty() { continue; }
+		let miptex: &Miptex = match source {
+			TextureSource::Embedded => miptex,
+			TextureSource::Wad(_, from_wad) => from_wad,
+			TextureSource::Missing | TextureSource::NotSearched => {
+				missing.push(&miptex.name);
+				continue;
+			},
+		};
+		// goldsrc (wad3) textures have their own palette, broken ones still get something out of the quake one
+		let palette: &[[u8; 3]] = if miptex.palette.len() == 256 { &miptex.palette } else { &palette };
+		let Some(rgba) = decode(miptex, 0, palette) else { continue };
+		write_png(&format!("{out_dir}/{}.png", file_name(&miptex.name)), miptex.width, miptex.height, &rgba);
+		exported += 1;
+	}
+
+	println!("exported {exported} textures to {out_dir}");
+	if !missing.is_empty() {
+		println!(
+			"{} textures aren't embedded in the map{}: {}",
+			missing.len(),
+			if game_dir.is_some() { " or in any of its wads" } else { " (give a game directory to look in its wads)" },
+			missing.join(", "),
+		);
+	}
+}
@@ -0,0 +1,23 @@
+pub mod q1bsp;
+pub mod decompile;
+pub mod collision;
+pub mod vbsp;
+pub mod lightmap;
+pub mod ambient;
+pub mod locate;
+pub mod trace;
+pub mod visible;
+pub mod areas;
+pub mod tree;
+pub mod miptex;
+pub mod wad;
+pub mod content;
+pub mod mdl;
+pub mod materials;
+pub mod vtf;
+pub mod cubemaps;
+pub mod props;
+pub mod textures;
+pub mod limits;
+pub mod validate;
+pub mod crc;
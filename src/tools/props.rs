@@ -0,0 +1,99 @@
+// checks the static prop models of a source map: every name in the sprp dictionary is looked up in the pakfile
+// and the game directory, and the mdl has to be compiled with $staticprop and have vertex files
+// with the same checksum. the engine skips props it can't load, so they just vanish in game
+
+use crate::{
+	lumps::vbsp::VBSPLumpType,
+	tools::{content::{Content, Location}, mdl::{vertex_file_checksum, Mdl}, vbsp::VBsp},
+	utils::bitflags_to_string,
+};
+
+// vtx files the engine might load, one is enough
+const VTX_EXTENSIONS: [&str; 4] = [".dx90.vtx", ".dx80.vtx", ".sw.vtx", ".vtx"];
+
+fn plural(count: usize) -> &'static str {
+	if count == 1 { "" } else { "s" }
+}
+
+// the problems with a model's vertex files
+fn check_vertex_files(content: &Content, model: &str, checksum: i32) -> Vec<String> {
+	let base: &str = model.trim_end_matches(".mdl");
+	let check = |path: &str, vtx: bool| match content.read(path).map(|b| vertex_file_checksum(&b, vtx)) {
+		Ok(Some(c)) if c == checksum => None,
+		Ok(Some(c)) => Some(format!("{path} has checksum {c:#010x} but the mdl has {checksum:#010x}")),
+		Ok(None) => Some(format!("{path} is broken")),
+		Err(e) => Some(e),
+	};
+
+	let mut problems: Vec<String> = vec![];
+	let vvd: String = format!("{base}.vvd");
+	match content.find(&vvd) {
+		Some(_) => problems.extend(check(&vvd, false)),
+		None => problems.push(format!("{vvd} not found")),
+	}
+	match VTX_EXTENSIONS.iter().map(|ext| format!("{base}{ext}")).find(|p| content.find(p).is_some()) {
+		Some(vtx) => problems.extend(check(&vtx, true)),
+		None => problems.push(format!("no {base}.dx90.vtx (or any other vtx)")),
+	}
+	problems
+}
+
+pub fn check(ld: &[VBSPLumpType], game_dir: Option<&str>) {
+	let bsp: VBsp = VBsp::from_lumps(ld);
+	let Some(props) = bsp.static_props else {
+		println!("this map has no static prop lump");
+		return;
+	};
+	let content: Content = Content::new(bsp.pakfile, game_dir);
+
+	let mut counts: Vec<usize> = vec![0; props.dict.names.len()];
+	let mut out_of_range: usize = 0;
+	for prop_type in &props.prop_types {
+		match counts.get_mut(*prop_type as usize) {
+			Some(count) => *count += 1,
+			None => out_of_range += 1,
+		}
+	}
+
+	println!("{} static prop models, {} props", props.dict.names.len(), props.prop_types.len());
+	let (mut missing, mut broken): (Vec<String>, Vec<String>) = (vec![], vec![]);
+	for (model, count) in props.dict.names.iter().zip(&counts) {
+		let used: String = format!("{count} prop{}", plural(*count));
+		let Some(location) = content.find(model) else {
+			missing.push(format!("\t{model} ({used})"));
+			continue;
+		};
+		let mdl: Mdl = match content.read(model).and_then(|b| Mdl::parse(&b)) {
+			Ok(mdl) => mdl,
+			Err(e) => {
+				broken.push(format!("\t{model} ({used}): {e}"));
+				continue;
+			},
+		};
+		println!(
+			"\t[{}] {model}: {used}, version {}, checksum {:#010x}, flags: {}",
+			match location { Location::Pak => "pak", Location::Loose(_) => "game" },
+			mdl.version, mdl.checksum, bitflags_to_string(mdl.flags.iter_names()),
+		);
+
+		let mut problems: Vec<String> = vec![];
+		if !mdl.is_static_prop() { problems.push("not compiled with $staticprop".to_string()); }
+		problems.extend(check_vertex_files(&content, model, mdl.checksum));
+		for problem in problems {
+			broken.push(format!("\t{model} ({used}): {problem}"));
+		}
+	}
+
+	println!("\nmissing models ({}):", missing.len());
+	for line in &missing { println!("{line}"); }
+	println!("\nincompatible or broken models ({}):", broken.len());
+	for line in &broken { println!("{line}"); }
+	if out_of_range > 0 {
+		println!("\n{out_of_range} prop{} a model index past the end of the dictionary", if out_of_range == 1 { " uses" } else { "s use" });
+	}
+	println!(
+		"\n{} missing, {} problems{}",
+		missing.len(), broken.len(),
+		if content.game_dir.is_some() { "" } else { " (only the pakfile was searched)" },
+	);
+}
@@ -0,0 +1,463 @@
+// goldsrc and quake maps are laid out the same way
+// (goldsrc just adds an embedded palette to its textures and rgb lighting)
+// so the tools work on this merged copy of the lumps instead of
+// having to match on two almost identical sets of structs everywhere
+
+use crate::{
+	file_structure::BSPFile,
+	flags::GoldSrcContentsFlags,
+	geometry::Plane,
+	lumps::{
+		goldsrc::GoldSrcLumpType,
+		lumptype::Lumps,
+		quake::QuakeLumpType,
+	},
+	specific::vis::{decompress_vis, BitSet},
+	utils::Vector3,
+};
+
+pub const CONTENTS_EMPTY: i32 = -1;
+pub const CONTENTS_SOLID: i32 = -2;
+pub const CONTENTS_SKY: i32 = -6;
+
+// contents values arent real flags, every value is its own thing
+pub fn contents_name(contents: i32) -> &'static str {
+	<GoldSrcContentsFlags as bitflags::Flags>::FLAGS.iter()
+		.find(|f| f.value().bits() == contents)
+		.map_or("Unknown", |f| f.name())
+}
+
+#[derive(Debug, Clone)]
+#[allow(unused)]
+pub struct Miptex {
+	pub name: String,
+	pub width: u32,
+	pub height: u32,
+	pub offsets: [u32; 4],
+	// offset of the miptex struct from the start of the file
+	pub file_offset: usize,
+	pub mips: Vec<Vec<u8>>, // empty if the texture isnt in the bsp
+	pub palette: Vec<[u8; 3]>, // goldsrc only, quake uses palette.lmp
+}
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub struct Node {
+	pub plane: usize,
+	// negative numbers are -(leaf + 1)
+	pub children: [i32; 2],
+	pub mins: [i16; 3],
+	pub maxs: [i16; 3],
+	pub first_face: usize,
+	pub num_faces: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub struct TexInfo {
+	pub s: Vector3,
+	pub s_shift: f32,
+	pub t: Vector3,
+	pub t_shift: f32,
+	pub miptex: i32,
+	pub flags: i32,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub struct Face {
+	pub plane: usize,
+	pub side: bool, // true if the face looks the opposite way of its plane
+	pub first_edge: usize,
+	pub num_edges: usize,
+	pub texinfo: usize,
+	pub styles: [u8; 4],
+	pub lightofs: i32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ClipNode {
+	pub plane: usize,
+	// negative numbers are contents values
+	pub children: [i32; 2],
+}
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub struct Leaf {
+	pub contents: i32,
+	pub visofs: i32,
+	pub mins: [i16; 3],
+	pub maxs: [i16; 3],
+	pub first_marksurface: usize,
+	pub num_marksurfaces: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[allow(unused)]
+pub struct Model {
+	pub mins: Vector3,
+	pub maxs: Vector3,
+	pub origin: Vector3,
+	pub headnodes: [i32; 4],
+	pub visleafs: i32,
+	pub first_face: usize,
+	pub num_faces: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Q1Bsp {
+	pub goldsrc: bool,
+	pub entities: Vec<Vec<(String, String)>>,
+	pub planes: Vec<Plane>,
+	pub miptexs: Vec<Miptex>,
+	pub vertices: Vec<Vector3>,
+	pub nodes: Vec<Node>,
+	pub texinfos: Vec<TexInfo>,
+	pub faces: Vec<Face>,
+	pub clipnodes: Vec<ClipNode>,
+	pub leaves: Vec<Leaf>,
+	pub marksurfaces: Vec<u16>,
+	pub edges: Vec<[u16; 2]>,
+	pub surfedges: Vec<i32>,
+	pub models: Vec<Model>,
+	pub lighting: Vec<u8>, // rgb triplets for goldsrc, single bytes for quake
+	pub visibility: Vec<u8>, // compressed
+}
+
+impl Q1Bsp {
+	fn empty(goldsrc: bool) -> Q1Bsp {
+		Q1Bsp {
+			goldsrc,
+			entities: vec![], planes: vec![], miptexs: vec![], vertices: vec![],
+			nodes: vec![], texinfos: vec![], faces: vec![], clipnodes: vec![],
+			leaves: vec![], marksurfaces: vec![], edges: vec![], surfedges: vec![],
+			models: vec![], lighting: vec![], visibility: vec![],
+		}
+	}
+
+	// none for vbsp
+	pub fn from_file(file: &BSPFile) -> Option<Q1Bsp> {
+		let textures_offset: u32 = file.header.lumps[2].file_offset;
+		match &file.lump_data {
+			Lumps::GoldSrc(ld) => Some(Q1Bsp::from_goldsrc(ld, textures_offset)),
+			Lumps::Quake(ld) => Some(Q1Bsp::from_quake(ld, textures_offset)),
+			Lumps::VBSP(_) => None,
+		}
+	}
+
+	// textures lump offset is needed to know where the mip data lives
+	pub fn from_goldsrc(ld: &[GoldSrcLumpType], textures_offset: u32) -> Q1Bsp {
+		let mut bsp: Q1Bsp = Q1Bsp::empty(true);
+		for lump in ld {
+			match lump {
+				GoldSrcLumpType::Entities(ents) => bsp.entities = ents.clone(),
+				GoldSrcLumpType::Planes(planes) => bsp.planes = planes.iter()
+					.map(|p| Plane { normal: p.normal, dist: p.dist })
+					.collect(),
+				GoldSrcLumpType::Textures(textures) => bsp.miptexs = textures.miptexs.iter()
+					.zip(&textures.offsets)
+					.map(|(m, ofs)| Miptex {
+						name: m.name.split('\0').next().unwrap().to_string(),
+						width: m.width,
+						height: m.height,
+						offsets: m.offsets,
+						file_offset: textures_offset as usize + *ofs as usize,
+						mips: m.mips.clone(),
+						palette: m.palette.clone(),
+					})
+					.collect(),
+				GoldSrcLumpType::Vertices(verts) => bsp.vertices = verts.clone(),
+				GoldSrcLumpType::Visibility(vis) => bsp.visibility = vis.clone(),
+				GoldSrcLumpType::Nodes(nodes) => bsp.nodes = nodes.iter()
+					.map(|n| Node {
+						plane: n.plane_idx as usize,
+						children: [n.children_idxs[0] as i32, n.children_idxs[1] as i32],
+						mins: n.mins,
+						maxs: n.maxs,
+						first_face: n.first_face as usize,
+						num_faces: n.num_faces as usize,
+					})
+					.collect(),
+				GoldSrcLumpType::TexInfo(texinfos) => bsp.texinfos = texinfos.iter()
+					.map(|t| TexInfo {
+						s: t.s, s_shift: t.s_shift,
+						t: t.t, t_shift: t.t_shift,
+						miptex: t.miptex_idx as i32,
+						flags: t.flags as i32,
+					})
+					.collect(),
+				GoldSrcLumpType::Faces(faces) => bsp.faces = faces.iter()
+					.map(|f| Face {
+						plane: f.plane_idx as usize,
+						side: f.plane_side != 0,
+						first_edge: f.first_surfedge_idx as usize,
+						num_edges: f.num_surfedges as usize,
+						texinfo: f.texinfo_idx as usize,
+						styles: f.styles,
+						lightofs: f.lightmap_offset,
+					})
+					.collect(),
+				GoldSrcLumpType::ClipNodes(clipnodes) => bsp.clipnodes = clipnodes.iter()
+					.map(|c| ClipNode {
+						plane: c.plane_idx as usize,
+						children: [c.children_idxs[0] as i32, c.children_idxs[1] as i32],
+					})
+					.collect(),
+				GoldSrcLumpType::Leaves(leaves) => bsp.leaves = leaves.iter()
+					.map(|l| Leaf {
+						contents: l.contents.bits(),
+						visofs: l.vis_ofs,
+						mins: l.mins,
+						maxs: l.maxs,
+						first_marksurface: l.first_maksurf_idx as usize,
+						num_marksurfaces: l.num_marksurfaces as usize,
+					})
+					.collect(),
+				GoldSrcLumpType::Lighting(lightmaps) => bsp.lighting = lightmaps.iter()
+					.flat_map(|l| l.color)
+					.collect(),
+				GoldSrcLumpType::MarkSurfaces(marksurfs) => bsp.marksurfaces = marksurfs.clone(),
+				GoldSrcLumpType::Edges(edges) => bsp.edges = edges.clone(),
+				GoldSrcLumpType::SurfEdges(surfedges) => bsp.surfedges = surfedges.clone(),
+				GoldSrcLumpType::Models(models) => bsp.models = models.iter()
+					.map(|m| Model {
+						mins: m.mins, maxs: m.maxs, origin: m.origin,
+						headnodes: m.headnodes_idx,
+						visleafs: m.num_visleafs,
+						first_face: m.first_face_idx as usize,
+						num_faces: m.num_faces as usize,
+					})
+					.collect(),
+				_ => {},
+			}
+		}
+		bsp
+	}
+
+	pub fn from_quake(ld: &[QuakeLumpType], textures_offset: u32) -> Q1Bsp {
+		let mut bsp: Q1Bsp = Q1Bsp::empty(false);
+		for lump in ld {
+			match lump {
+				QuakeLumpType::Entities(ents) => bsp.entities = ents.clone(),
+				QuakeLumpType::Planes(planes) => bsp.planes = planes.iter()
+					.map(|p| Plane { normal: p.normal, dist: p.dist })
+					.collect(),
+				QuakeLumpType::Textures(texture) => {
+					// missing textures have an offset of -1 and arent parsed,
+					// keep a blank entry for them so the texinfo indices still line up
+					let mut miptexs = texture.miptexs.iter();
+					bsp.miptexs = texture.data_offset.iter()
+						.map(|ofs| match ofs {
+							-1 => Miptex {
+								name: "".to_string(), width: 0, height: 0,
+								offsets: [0; 4], file_offset: 0, mips: vec![], palette: vec![],
+							},
+							_ => {
+								let m = miptexs.next().unwrap();
+								Miptex {
+									name: m.name.clone(),
+									width: m.width,
+									height: m.height,
+									offsets: m.offsets,
+									file_offset: textures_offset as usize + *ofs as usize,
+									mips: m.mips.clone(),
+									palette: vec![],
+								}
+							},
+						})
+						.collect();
+				},
+				QuakeLumpType::Vertices(verts) => bsp.vertices = verts.iter()
+					.map(|v| v.point)
+					.collect(),
+				QuakeLumpType::Visibility(vis) => bsp.visibility = vis.clone(),
+				QuakeLumpType::Nodes(nodes) => bsp.nodes = nodes.iter()
+					.map(|n| Node {
+						plane: n.planenum as usize,
+						children: [n.children[0] as i32, n.children[1] as i32],
+						mins: n.mins,
+						maxs: n.maxs,
+						first_face: n.first_face as usize,
+						num_faces: n.num_faces as usize,
+					})
+					.collect(),
+				QuakeLumpType::TexInfo(texinfos) => bsp.texinfos = texinfos.iter()
+					.map(|t| TexInfo {
+						s: Vector3 { x: t.vecs[0][0], y: t.vecs[0][1], z: t.vecs[0][2] },
+						s_shift: t.vecs[0][3],
+						t: Vector3 { x: t.vecs[1][0], y: t.vecs[1][1], z: t.vecs[1][2] },
+						t_shift: t.vecs[1][3],
+						miptex: t.miptex,
+						flags: t.flags,
+					})
+					.collect(),
+				QuakeLumpType::Faces(faces) => bsp.faces = faces.iter()
+					.map(|f| Face {
+						plane: f.planenum as u16 as usize,
+						side: f.side != 0,
+						first_edge: f.first_edge as usize,
+						num_edges: f.num_edges as u16 as usize,
+						texinfo: f.texinfo as u16 as usize,
+						styles: f.styles,
+						lightofs: f.lightofs,
+					})
+					.collect(),
+				QuakeLumpType::ClipNodes(clipnodes) => bsp.clipnodes = clipnodes.iter()
+					.map(|c| ClipNode {
+						plane: c.planenum as usize,
+						children: [c.children[0] as i32, c.children[1] as i32],
+					})
+					.collect(),
+				QuakeLumpType::Leaves(leaves) => bsp.leaves = leaves.iter()
+					.map(|l| Leaf {
+						contents: l.contents.bits(),
+						visofs: l.visofs,
+						// these are stored as signed shorts in the file
+						mins: l.mins.map(|v| v as i16),
+						maxs: l.maxs.map(|v| v as i16),
+						first_marksurface: l.first_marksurface as usize,
+						num_marksurfaces: l.num_marksurfaces as usize,
+					})
+					.collect(),
+				QuakeLumpType::Lighting(lighting) => bsp.lighting = lighting.clone(),
+				QuakeLumpType::MarkSurfaces(marksurfs) => bsp.marksurfaces = marksurfs.clone(),
+				QuakeLumpType::Edges(edges) => bsp.edges = edges.iter().map(|e| e.v).collect(),
+				QuakeLumpType::SurfEdges(surfedges) => bsp.surfedges = surfedges.clone(),
+				QuakeLumpType::Models(models) => bsp.models = models.iter()
+					.map(|m| Model {
+						mins: m.mins, maxs: m.maxs, origin: m.origin,
+						headnodes: m.headnode,
+						visleafs: m.visleafs,
+						first_face: m.firstface as usize,
+						num_faces: m.numfaces as usize,
+					})
+					.collect(),
+				_ => {},
+			}
+		}
+		bsp
+	}
+
+	// leaves with vis data, these are leaves 1 to visleafs (world leaves only)
+	pub fn num_visleafs(&self) -> usize {
+		self.models.first().map_or(0, |m| m.visleafs.max(0) as usize)
+	}
+
+	// which visleafs can be seen from a leaf, index i is leaf i + 1
+	// leaves without vis info (or maps that were never vised) see everything like in the engine,
+	// the solid leaf 0 sees nothing
+	pub fn leaf_pvs(&self, leaf: usize) -> BitSet {
+		let num_visleafs: usize = self.num_visleafs();
+		if leaf == 0 || leaf > num_visleafs { return BitSet::new(num_visleafs); }
+		match self.leaves.get(leaf).map(|l| l.visofs) {
+			Some(ofs) if ofs >= 0 && !self.visibility.is_empty() => decompress_vis(
+				self.visibility.get(ofs as usize..).unwrap_or(&[]),
+				num_visleafs,
+			),
+			_ => BitSet::filled(num_visleafs),
+		}
+	}
+
+	// same as looking it up in leaf_pvs but only decodes the row of the first leaf
+	pub fn leaf_can_see(&self, from: usize, to: usize) -> bool {
+		to > 0 && self.leaf_pvs(from).get(to - 1)
+	}
+
+	// the whole pvs, row and column i are both leaf i + 1
+	pub fn pvs_matrix(&self) -> Vec<BitSet> {
+		(1..=self.num_visleafs()).map(|l| self.leaf_pvs(l)).collect()
+	}
+
+	// the polygon of a face in the order the surfedges list it, broken indices are skipped
+	pub fn face_vertices(&self, face: &Face) -> Vec<Vector3> {
+		let mut verts: Vec<Vector3> = vec![];
+		for i in 0..face.num_edges {
+			let Some(surfedge) = face.first_edge.checked_add(i).and_then(|s| self.surfedges.get(s)) else { break };
+			let Some(edge) = self.edges.get(surfedge.unsigned_abs() as usize) else { continue };
+			let v: u16 = if *surfedge < 0 { edge[1] } else { edge[0] };
+			let Some(vertex) = self.vertices.get(v as usize) else { continue };
+			verts.push(*vertex);
+		}
+		verts
+	}
+
+	// the plane the face actually looks along
+	pub fn face_plane(&self, face: &Face) -> Option<Plane> {
+		let plane: Plane = *self.planes.get(face.plane)?;
+		Some(if face.side { plane.flipped() } else { plane })
+	}
+
+	// texture space mins (in luxels) and the lightmap size of a face,
+	// same as CalcSurfaceExtents in the engine (a luxel is 16 texels)
+	pub fn lightmap_extents(&self, face: &Face) -> Option<([i32; 2], [u32; 2])> {
+		let texinfo: &TexInfo = self.texinfos.get(face.texinfo)?;
+		let verts: Vec<Vector3> = self.face_vertices(face);
+		let mut mins: [i32; 2] = [0; 2];
+		let mut size: [u32; 2] = [0; 2];
+		for (i, (axis, shift)) in [(texinfo.s, texinfo.s_shift), (texinfo.t, texinfo.t_shift)].iter().enumerate() {
+			let vals: Vec<f32> = verts.iter().map(|v| v.dot(axis) + shift).collect();
+			let min: f32 = (vals.iter().cloned().fold(f32::MAX, f32::min) / 16.0).floor();
+			let max: f32 = (vals.iter().cloned().fold(f32::MIN, f32::max) / 16.0).ceil();
+			mins[i] = min as i32;
+			size[i] = (max - min).max(0.0) as u32 + 1;
+		}
+		Some((mins, size))
+	}
+
+	// every lightmap of the face as (style, rgb luxels), quake ones are grayscale
+	pub fn face_lightmaps(&self, face: &Face) -> Vec<(u8, Vec<[u8; 3]>)> {
+		let mut res: Vec<(u8, Vec<[u8; 3]>)> = vec![];
+		if face.lightofs < 0 || face.num_edges == 0 { return res; }
+		let Some((_, size)) = self.lightmap_extents(face) else { return res };
+		let luxels: usize = (size[0] * size[1]) as usize;
+		let bytes_per_luxel: usize = if self.goldsrc { 3 } else { 1 };
+
+		for (i, style) in face.styles.iter().take_while(|s| **s != 255).enumerate() {
+			let start: usize = face.lightofs as usize + i * luxels * bytes_per_luxel;
+			let Some(data) = self.lighting.get(start..start + luxels * bytes_per_luxel) else { break };
+			res.push((*style, data.chunks(bytes_per_luxel)
+				.map(|c| if self.goldsrc { [c[0], c[1], c[2]] } else { [c[0]; 3] })
+				.collect()));
+		}
+		res
+	}
+
+	pub fn texture_name(&self, texinfo: &TexInfo) -> Option<&str> {
+		self.miptexs.get(texinfo.miptex as usize).map(|m| m.name.as_str())
+	}
+
+	// walks the hull 0 tree of a model, returns the leaf index
+	// broken node or plane indices (or a loop in the tree) end up in the solid leaf 0
+	pub fn leaf_at(&self, model: usize, point: &Vector3) -> Option<usize> {
+		let solid: Option<usize> = (!self.leaves.is_empty()).then_some(0);
+		let Some(m) = self.models.get(model) else { return solid };
+		let mut node: i32 = m.headnodes[0];
+		// a path down the tree can't pass more nodes than there are
+		for _ in 0..=self.nodes.len() {
+			if node < 0 {
+				let leaf: usize = (-1 - node) as usize;
+				return if leaf < self.leaves.len() { Some(leaf) } else { solid };
+			}
+			let Some(n) = self.nodes.get(node as usize) else { return solid };
+			let Some(plane) = self.planes.get(n.plane) else { return solid };
+			let d: f32 = plane.distance_to(point);
+			node = n.children[if d >= 0.0 { 0 } else { 1 }];
+		}
+		solid
+	}
+
+	// walks one of the clipping hulls (1-3), returns a contents value
+	pub fn hull_contents(&self, model: usize, hull: usize, point: &Vector3) -> i32 {
+		let Some(m) = self.models.get(model) else { return CONTENTS_SOLID };
+		let mut node: i32 = m.headnodes[hull];
+		for _ in 0..=self.clipnodes.len() {
+			if node < 0 { return node; }
+			let Some(n) = self.clipnodes.get(node as usize) else { return CONTENTS_SOLID };
+			let Some(plane) = self.planes.get(n.plane) else { return CONTENTS_SOLID };
+			let d: f32 = plane.distance_to(point);
+			node = n.children[if d >= 0.0 { 0 } else { 1 }];
+		}
+		CONTENTS_SOLID
+	}
+}
@@ -0,0 +1,170 @@
+// how much of the map every texture covers: faces are joined through their texinfo to a texdata (source)
+// or a miptex (quake and goldsrc), the areas are summed and the brush models and surface flags collected.
+// sorted by area so the textures worth the most budget come first
+
+use std::{fs, io::Write};
+use crate::{
+	file_structure::BSPFile,
+	flags::SurfaceFlags,
+	lumps::lumptype::Lumps,
+	tools::{q1bsp::Q1Bsp, vbsp::VBsp},
+	utils::{bitflags_to_string, entity_value, int_to_quake_texflag, json_string, Vector3},
+};
+
+pub struct TextureUsage {
+	pub name: String,
+	pub width: i32,
+	pub height: i32,
+	pub faces: usize,
+	pub area: f64,
+	pub models: Vec<usize>, // 0 is the world
+	pub flags: String,
+}
+
+// sums the areas per texture, texture_of gives the texture index and flags of a face
+fn collect<F: Fn(usize) -> Option<(usize, u32)>>(
+	textures: &mut [TextureUsage],
+	face_areas: &[f64],
+	model_faces: &[(usize, usize)],
+	texture_of: F,
+) -> Vec<u32> {
+	let mut flags: Vec<u32> = vec![0; textures.len()];
+	for (model, (first, count)) in model_faces.iter().enumerate() {
+		for (face, area) in face_areas.iter().enumerate().skip(*first).take(*count) {
+			let Some((texture, face_flags)) = texture_of(face) else { continue };
+			let Some(usage) = textures.get_mut(texture) else { continue };
+			usage.faces += 1;
+			usage.area += area;
+			if !usage.models.contains(&model) { usage.models.push(model); }
+			flags[texture] |= face_flags;
+		}
+	}
+	flags
+}
+
+// area of a planar polygon
+fn polygon_area(verts: &[Vector3]) -> f64 {
+	let mut sum: Vector3 = Vector3::new();
+	for i in 1..verts.len().saturating_sub(1) {
+		sum = sum + (verts[i] - verts[0]).cross(&(verts[i + 1] - verts[0]));
+	}
+	sum.length() as f64 / 2.0
+}
+
+fn from_vbsp(bsp: &VBsp) -> Vec<TextureUsage> {
+	let mut textures: Vec<TextureUsage> = bsp.texdata.iter().enumerate()
+		.map(|(i, t)| TextureUsage {
+			name: bsp.texdata_name(i).unwrap_or("").to_string(),
+			width: t.width,
+			height: t.height,
+			faces: 0,
+			area: 0.0,
+			models: vec![],
+			flags: String::new(),
+		})
+		.collect();
+	// vbsp already stores the area of every face
+	let face_areas: Vec<f64> = bsp.faces.iter().map(|f| f.area as f64).collect();
+	let model_faces: Vec<(usize, usize)> = bsp.models.iter()
+		.map(|m| (m.first_face.max(0) as usize, m.num_faces.max(0) as usize))
+		.collect();
+	let flags: Vec<u32> = collect(&mut textures, &face_areas, &model_faces, |face| {
+		let texinfo = bsp.texinfos.get(bsp.faces[face].tex_info as usize)?;
+		Some((texinfo.texdata as usize, texinfo.flags.bits()))
+	});
+	for (usage, bits) in textures.iter_mut().zip(flags) {
+		usage.flags = bitflags_to_string(SurfaceFlags::from_bits_retain(bits).iter_names());
+	}
+	textures
+}
+
+fn from_q1(bsp: &Q1Bsp) -> Vec<TextureUsage> {
+	let mut textures: Vec<TextureUsage> = bsp.miptexs.iter()
+		.map(|m| TextureUsage {
+			name: m.name.clone(),
+			width: m.width as i32,
+			height: m.height as i32,
+			faces: 0,
+			area: 0.0,
+			models: vec![],
+			flags: String::new(),
+		})
+		.collect();
+	let face_areas: Vec<f64> = bsp.faces.iter().map(|f| polygon_area(&bsp.face_vertices(f))).collect();
+	let model_faces: Vec<(usize, usize)> = bsp.models.iter().map(|m| (m.first_face, m.num_faces)).collect();
+	let flags: Vec<u32> = collect(&mut textures, &face_areas, &model_faces, |face| {
+		let texinfo = bsp.texinfos.get(bsp.faces[face].texinfo)?;
+		Some((texinfo.miptex as usize, texinfo.flags as u32))
+	});
+	// TEX_SPECIAL is the only flag there is
+	for (usage, bits) in textures.iter_mut().zip(flags) {
+		usage.flags = int_to_quake_texflag(&(bits as i32)).to_string();
+	}
+	textures
+}
+
+// "world" or "*2 (func_door)"
+fn model_name(entities: &[Vec<(String, String)>], model: usize) -> String {
+	if model == 0 { return "world".to_string(); }
+	let key: String = format!("*{model}");
+	match entities.iter().find(|e| entity_value(e, "model") == Some(key.as_str())) {
+		Some(ent) => format!("{key} ({})", entity_value(ent, "classname").unwrap_or("")),
+		None => key,
+	}
+}
+
+fn to_table(textures: &[TextureUsage], entities: &[Vec<(String, String)>]) -> String {
+	let total: f64 = textures.iter().map(|t| t.area).sum::<f64>().max(1.0);
+	let mut res: String = format!(
+		"{:>12} {:>6} {:>6} {:>9}  {:<40} {:<30} models\n",
+		"area", "%", "faces", "size", "texture", "flags",
+	);
+	for t in textures {
+		res.push_str(&format!(
+			"{:>12.0} {:>5.1}% {:>6} {:>9}  {:<40} {:<30} {}\n",
+			t.area, t.area / total * 100.0, t.faces, format!("{}x{}", t.width, t.height), t.name, t.flags,
+			t.models.iter().map(|m| model_name(entities, *m)).collect::<Vec<String>>().join(", "),
+		));
+	}
+	res
+}
+
+fn to_json(textures: &[TextureUsage]) -> String {
+	let entries: Vec<String> = textures.iter()
+		.map(|t| format!(
+			"{{\"name\":{},\"width\":{},\"height\":{},\"faces\":{},\"area\":{},\"models\":{:?},\"flags\":{}}}",
+			json_string(&t.name), t.width, t.height, t.faces, t.area, t.models, json_string(&t.flags),
+		))
+		.collect();
+	format!("{{\"textures\":[{}]}}\n", entries.join(","))
+}
+
+pub fn report(path: String, file: &BSPFile, format: &str) {
+	let (mut textures, entities): (Vec<TextureUsage>, Vec<Vec<(String, String)>>) = match &file.lump_data {
+		Lumps::VBSP(ld) => {
+			let bsp: VBsp = VBsp::from_lumps(ld);
+			(from_vbsp(&bsp), bsp.entities.to_vec())
+		},
+		_ => {
+			let bsp: Q1Bsp = Q1Bsp::from_file(file).unwrap();
+			(from_q1(&bsp), bsp.entities)
+		},
+	};
+	let unused: usize = textures.iter().filter(|t| t.faces == 0).count();
+	textures.retain(|t| t.faces > 0);
+	textures.sort_by(|a, b| b.area.total_cmp(&a.area).then(b.faces.cmp(&a.faces)));
+
+	match format {
+		"table" => {
+			print!("{}", to_table(&textures, &entities));
+			println!("\n{} textures on faces, {unused} not used by any face", textures.len());
+		},
+		"json" => {
+			let out_path: String = path.trim_end_matches(".bsp").to_owned() + "-texture_usage.json";
+			let mut out: fs::File = fs::File::create(&out_path).unwrap();
+			out.write_all(to_json(&textures).as_bytes()).unwrap();
+			println!("exported {} textures to {out_path}", textures.len());
+		},
+		_ => println!("unknown texture report format {format}! (expected table or json)"),
+	}
+}
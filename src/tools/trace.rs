@@ -0,0 +1,484 @@
+// line and box traces against the map
+// source maps are traced against the brushes in the leaves the trace goes through (like CM_BoxTrace),
+// quake and goldsrc maps against one of the clipping hulls (like SV_RecursiveHullCheck)
+
+use crate::{
+	file_structure::{BSPFile, BSPVersion},
+	flags::{ContentsFlags, SurfaceFlags},
+	geometry::Plane,
+	lumps::{lumptype::Lumps, vbsp},
+	tools::{q1bsp::{self, Q1Bsp, CONTENTS_EMPTY, CONTENTS_SOLID}, vbsp::VBsp},
+	utils::{bitflags_to_string, Vector3},
+};
+
+// how far traces stay away from the planes they hit
+const DIST_EPSILON: f32 = 0.03125;
+
+pub const MASK_ALL: ContentsFlags = ContentsFlags::all();
+pub const MASK_SOLID: ContentsFlags = ContentsFlags::Solid
+	.union(ContentsFlags::Moveable)
+	.union(ContentsFlags::Window)
+	.union(ContentsFlags::Monster)
+	.union(ContentsFlags::Grate);
+pub const MASK_PLAYERSOLID: ContentsFlags = MASK_SOLID.union(ContentsFlags::PlayerClip);
+pub const MASK_NPCSOLID: ContentsFlags = MASK_SOLID.union(ContentsFlags::MonsterClip);
+pub const MASK_SHOT: ContentsFlags = ContentsFlags::Solid
+	.union(ContentsFlags::Moveable)
+	.union(ContentsFlags::Monster)
+	.union(ContentsFlags::Window)
+	.union(ContentsFlags::Debris)
+	.union(ContentsFlags::Hitbox);
+pub const MASK_OPAQUE: ContentsFlags = ContentsFlags::Solid
+	.union(ContentsFlags::Moveable)
+	.union(ContentsFlags::Opaque);
+
+#[derive(Debug, Clone, Copy)]
+pub enum TraceContents {
+	Source(ContentsFlags),
+	Quake(i32), // one of the CONTENTS_ values
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Trace {
+	pub fraction: f32, // 1 if nothing was hit
+	pub end: Vector3,
+	pub plane: Option<Plane>, // facing the way the trace came from
+	pub contents: TraceContents, // of whatever was hit
+	pub surface_flags: SurfaceFlags, // always empty for quake and goldsrc, the hulls have no surfaces
+	pub start_solid: bool,
+	pub all_solid: bool,
+}
+
+impl Trace {
+	pub fn print(&self) {
+		println!("\tfraction: {}", self.fraction);
+		println!("\tend: {}", self.end);
+		if let Some(plane) = &self.plane {
+			println!("\tplane: normal {}, dist {}", plane.normal, plane.dist);
+		}
+		match self.contents {
+			TraceContents::Source(c) => println!("\tcontents: {}", bitflags_to_string(c.iter_names())),
+			TraceContents::Quake(c) => println!("\tcontents: {}", q1bsp::contents_name(c)),
+		}
+		if !self.surface_flags.is_empty() {
+			println!("\tsurface flags: {}", bitflags_to_string(self.surface_flags.iter_names()));
+		}
+		if self.start_solid { println!("\tstarted in solid"); }
+		if self.all_solid { println!("\tentirely in solid"); }
+	}
+}
+
+fn lerp(start: &Vector3, end: &Vector3, frac: f32) -> Vector3 {
+	*start + (*end - *start) * frac
+}
+
+//
+// source
+//
+
+struct BoxTrace<'a> {
+	bsp: &'a VBsp<'a>,
+	start: Vector3,
+	end: Vector3,
+	mins: Vector3,
+	maxs: Vector3,
+	extents: Vector3,
+	is_point: bool,
+	mask: ContentsFlags,
+	checked: Vec<bool>, // brushes already clipped against
+	visited: Vec<bool>, // nodes already walked, a broken tree can loop back
+	res: Trace,
+}
+
+// traces a box from start to end through a model (0 is the world)
+// mins and maxs are relative to the box origin, both zero makes it a ray
+pub fn trace_vbsp(
+	bsp: &VBsp,
+	model: usize,
+	start: Vector3,
+	end: Vector3,
+	mins: Vector3,
+	maxs: Vector3,
+	mask: ContentsFlags,
+) -> Trace {
+	let mut trace: BoxTrace = BoxTrace {
+		bsp,
+		start,
+		end,
+		mins,
+		maxs,
+		extents: Vector3 {
+			x: mins.x.abs().max(maxs.x.abs()),
+			y: mins.y.abs().max(maxs.y.abs()),
+			z: mins.z.abs().max(maxs.z.abs()),
+		},
+		is_point: mins.to_array() == [0.0; 3] && maxs.to_array() == [0.0; 3],
+		mask,
+		checked: vec![false; bsp.brushes.len()],
+		visited: vec![false; bsp.nodes.len()],
+		res: Trace {
+			fraction: 1.0,
+			end,
+			plane: None,
+			contents: TraceContents::Source(ContentsFlags::empty()),
+			surface_flags: SurfaceFlags::empty(),
+			start_solid: false,
+			all_solid: false,
+		},
+	};
+	let head_node: i32 = bsp.models.get(model).map_or(0, |m| m.head_node);
+	trace.recursive_check(head_node);
+
+	if trace.res.all_solid {
+		trace.res.fraction = 0.0;
+	}
+	trace.res.end = lerp(&start, &end, trace.res.fraction);
+	trace.res
+}
+
+impl BoxTrace<'_> {
+	// goes into every leaf the swept box touches
+	fn recursive_check(&mut self, num: i32) {
+		if self.res.fraction == 0.0 { return; }
+		if num < 0 {
+			self.trace_to_leaf((-1 - num) as usize);
+			return;
+		}
+		if self.visited.get(num as usize) != Some(&false) { return; }
+		self.visited[num as usize] = true;
+		let node: &vbsp::Node = &self.bsp.nodes[num as usize];
+		let Some(plane) = self.bsp.planes.get(node.plane_num as usize) else { return };
+
+		let t1: f32 = plane.normal.dot(&self.start) - plane.dist;
+		let t2: f32 = plane.normal.dot(&self.end) - plane.dist;
+		let offset: f32 = if self.is_point { 0.0 } else {
+			(self.extents.x * plane.normal.x).abs()
+				+ (self.extents.y * plane.normal.y).abs()
+				+ (self.extents.z * plane.normal.z).abs()
+		};
+
+		if t1 >= offset && t2 >= offset {
+			self.recursive_check(node.children[0]);
+		} else if t1 < -offset && t2 < -offset {
+			self.recursive_check(node.children[1]);
+		} else {
+			self.recursive_check(node.children[0]);
+			self.recursive_check(node.children[1]);
+		}
+	}
+
+	fn trace_to_leaf(&mut self, leaf: usize) {
+		let Some(leaf) = self.bsp.leaves.get(leaf) else { return };
+		if !leaf.contents.intersects(self.mask) { return; }
+
+		let first: usize = leaf.first_leaf_brushes as usize;
+		for i in first..first + leaf.num_leaf_brushes as usize {
+			let Some(brush_index) = self.bsp.leaf_brushes.get(i).map(|b| *b as usize) else { break };
+			if self.checked.get(brush_index) != Some(&false) { continue; }
+			self.checked[brush_index] = true;
+			let brush: &vbsp::Brush = &self.bsp.brushes[brush_index];
+			if !brush.contents.intersects(self.mask) { continue; }
+			self.clip_to_brush(brush);
+			if self.res.fraction == 0.0 { return; }
+		}
+	}
+
+	fn clip_to_brush(&mut self, brush: &vbsp::Brush) {
+		let mut enter_frac: f32 = -1.0;
+		let mut leave_frac: f32 = 1.0;
+		let mut clip_plane: Option<Plane> = None;
+		let mut lead_side: Option<&vbsp::BrushSide> = None;
+		let mut get_out: bool = false;
+		let mut start_out: bool = false;
+
+		let first: usize = brush.first_side.max(0) as usize;
+		let Some(sides) = self.bsp.brush_sides.get(first..first + brush.num_sides.max(0) as usize) else { return };
+		for side in sides {
+			// rays dont hit bevels, those are only there to keep boxes from sticking out of corners
+			if self.is_point && side.bevel != 0 { continue; }
+			let Some(plane) = self.bsp.planes.get(side.plane_num as usize) else { return };
+
+			// push the plane out so the box touching it counts as the origin touching it
+			let dist: f32 = if self.is_point { plane.dist } else {
+				let ofs: Vector3 = Vector3 {
+					x: if plane.normal.x < 0.0 { self.maxs.x } else { self.mins.x },
+					y: if plane.normal.y < 0.0 { self.maxs.y } else { self.mins.y },
+					z: if plane.normal.z < 0.0 { self.maxs.z } else { self.mins.z },
+				};
+				plane.dist - ofs.dot(&plane.normal)
+			};
+			let d1: f32 = self.start.dot(&plane.normal) - dist;
+			let d2: f32 = self.end.dot(&plane.normal) - dist;
+
+			if d2 > 0.0 { get_out = true; }
+			if d1 > 0.0 { start_out = true; }
+			// completely in front of this side, cant hit the brush
+			if d1 > 0.0 && d2 >= d1 { return; }
+			// completely behind, this side wont be the one that gets hit
+			if d1 <= 0.0 && d2 <= 0.0 { continue; }
+
+			if d1 > d2 {
+				let f: f32 = (d1 - DIST_EPSILON) / (d1 - d2);
+				if f > enter_frac {
+					enter_frac = f;
+					clip_plane = Some(Plane { normal: plane.normal, dist: plane.dist });
+					lead_side = Some(side);
+				}
+			} else {
+				let f: f32 = (d1 + DIST_EPSILON) / (d1 - d2);
+				if f < leave_frac { leave_frac = f; }
+			}
+		}
+
+		if !start_out {
+			self.res.start_solid = true;
+			self.res.contents = TraceContents::Source(brush.contents);
+			if !get_out {
+				self.res.all_solid = true;
+				self.res.fraction = 0.0;
+			}
+			return;
+		}
+		if enter_frac < leave_frac && enter_frac > -1.0 && enter_frac < self.res.fraction {
+			self.res.fraction = enter_frac.max(0.0);
+			self.res.plane = clip_plane;
+			self.res.contents = TraceContents::Source(brush.contents);
+			self.res.surface_flags = lead_side
+				.and_then(|s| self.bsp.texinfos.get(s.texinfo as usize))
+				.map_or(SurfaceFlags::empty(), |t| t.flags);
+		}
+	}
+}
+
+//
+// quake and goldsrc
+//
+
+// size of the boxes the clipping hulls were made for, in goldsrc
+pub const GOLDSRC_HULLS: [(Vector3, Vector3); 4] = [
+	(Vector3 { x: 0.0, y: 0.0, z: 0.0 }, Vector3 { x: 0.0, y: 0.0, z: 0.0 }),
+	(Vector3 { x: -16.0, y: -16.0, z: -36.0 }, Vector3 { x: 16.0, y: 16.0, z: 36.0 }),
+	(Vector3 { x: -32.0, y: -32.0, z: -32.0 }, Vector3 { x: 32.0, y: 32.0, z: 32.0 }),
+	(Vector3 { x: -16.0, y: -16.0, z: -18.0 }, Vector3 { x: 16.0, y: 16.0, z: 18.0 }),
+];
+
+// and in quake (hull 3 isnt used there)
+pub const QUAKE_HULLS: [(Vector3, Vector3); 4] = [
+	(Vector3 { x: 0.0, y: 0.0, z: 0.0 }, Vector3 { x: 0.0, y: 0.0, z: 0.0 }),
+	(Vector3 { x: -16.0, y: -16.0, z: -24.0 }, Vector3 { x: 16.0, y: 16.0, z: 32.0 }),
+	(Vector3 { x: -32.0, y: -32.0, z: -24.0 }, Vector3 { x: 32.0, y: 32.0, z: 64.0 }),
+	(Vector3 { x: 0.0, y: 0.0, z: 0.0 }, Vector3 { x: 0.0, y: 0.0, z: 0.0 }),
+];
+
+struct HullTrace<'a> {
+	bsp: &'a Q1Bsp,
+	hull: usize,
+	head_node: i32,
+	visited: Vec<bool>, // same as in BoxTrace
+	res: Trace,
+}
+
+// traces the origin of a hull sized box from start to end through a model (0 is the world)
+// hull 0 is a plain ray through the drawing tree, 1-3 are the clipping hulls
+pub fn trace_q1(bsp: &Q1Bsp, model: usize, hull: usize, start: Vector3, end: Vector3) -> Trace {
+	let mut trace: HullTrace = HullTrace {
+		bsp,
+		hull,
+		head_node: bsp.models.get(model).and_then(|m| m.headnodes.get(hull)).copied().unwrap_or(0),
+		visited: vec![false; if hull == 0 { bsp.nodes.len() } else { bsp.clipnodes.len() }],
+		res: Trace {
+			fraction: 1.0,
+			end,
+			plane: None,
+			contents: TraceContents::Quake(CONTENTS_EMPTY),
+			surface_flags: SurfaceFlags::empty(),
+			start_solid: false,
+			all_solid: true,
+		},
+	};
+	trace.recursive_check(trace.head_node, 0.0, 1.0, start, end);
+
+	if trace.res.all_solid {
+		trace.res.start_solid = true;
+		trace.res.fraction = 0.0;
+		trace.res.end = start;
+	}
+	// only solid stops hull traces, otherwise its whatever the trace ended up in
+	trace.res.contents = TraceContents::Quake(match trace.res.fraction < 1.0 {
+		true => CONTENTS_SOLID,
+		false => trace.point_contents(trace.head_node, &end),
+	});
+	trace.res
+}
+
+impl HullTrace<'_> {
+	// (plane, children) of a node in the chosen hull
+	fn node(&self, num: i32) -> Option<(&Plane, [i32; 2])> {
+		let (plane, children): (usize, [i32; 2]) = match self.hull {
+			0 => self.bsp.nodes.get(num as usize).map(|n| (n.plane, n.children))?,
+			_ => self.bsp.clipnodes.get(num as usize).map(|c| (c.plane, c.children))?,
+		};
+		Some((self.bsp.planes.get(plane)?, children))
+	}
+
+	// hull 0 children point to leaves, the clipping hull ones are contents already
+	fn child_contents(&self, child: i32) -> i32 {
+		match self.hull {
+			0 => self.bsp.leaves.get((-1 - child) as usize).map_or(CONTENTS_SOLID, |l| l.contents),
+			_ => child,
+		}
+	}
+
+	fn point_contents(&self, mut num: i32, point: &Vector3) -> i32 {
+		// a path down the tree can't pass more nodes than there are
+		for _ in 0..=self.visited.len() {
+			if num < 0 { return self.child_contents(num); }
+			let Some((plane, children)) = self.node(num) else { return CONTENTS_SOLID };
+			num = children[if plane.distance_to(point) >= 0.0 { 0 } else { 1 }];
+		}
+		CONTENTS_SOLID
+	}
+
+	// returns false once something was hit
+	fn recursive_check(&mut self, num: i32, p1f: f32, p2f: f32, p1: Vector3, p2: Vector3) -> bool {
+		if num < 0 {
+			let contents: i32 = self.child_contents(num);
+			if contents != CONTENTS_SOLID {
+				self.res.all_solid = false;
+			} else {
+				self.res.start_solid = true;
+			}
+			return true;
+		}
+		if self.visited.get(num as usize) != Some(&false) { return true; }
+		self.visited[num as usize] = true;
+		let Some((plane, children)) = self.node(num) else { return true };
+		let plane: Plane = Plane { normal: plane.normal, dist: plane.dist };
+
+		let t1: f32 = plane.distance_to(&p1);
+		let t2: f32 = plane.distance_to(&p2);
+		if t1 >= 0.0 && t2 >= 0.0 {
+			return self.recursive_check(children[0], p1f, p2f, p1, p2);
+		}
+		if t1 < 0.0 && t2 < 0.0 {
+			return self.recursive_check(children[1], p1f, p2f, p1, p2);
+		}
+
+		// stay a little bit on the near side of the plane
+		let mut frac: f32 = if t1 < 0.0 {
+			(t1 + DIST_EPSILON) / (t1 - t2)
+		} else {
+			(t1 - DIST_EPSILON) / (t1 - t2)
+		}.clamp(0.0, 1.0);
+		let mut midf: f32 = p1f + (p2f - p1f) * frac;
+		let mut mid: Vector3 = lerp(&p1, &p2, frac);
+		let side: usize = if t1 < 0.0 { 1 } else { 0 };
+
+		if !self.recursive_check(children[side], p1f, midf, p1, mid) { return false; }
+		if self.point_contents(children[side ^ 1], &mid) != CONTENTS_SOLID {
+			return self.recursive_check(children[side ^ 1], midf, p2f, mid, p2);
+		}
+		if self.res.all_solid { return false; }
+
+		// the other side is solid, this is where it got hit
+		self.res.plane = Some(if side == 0 { plane } else { plane.flipped() });
+		// the epsilon can put mid inside of something else, back up until its not
+		while self.point_contents(self.head_node, &mid) == CONTENTS_SOLID {
+			frac -= 0.1;
+			if frac < 0.0 {
+				self.res.fraction = midf;
+				self.res.end = mid;
+				return false;
+			}
+			midf = p1f + (p2f - p1f) * frac;
+			mid = lerp(&p1, &p2, frac);
+		}
+		self.res.fraction = midf;
+		self.res.end = mid;
+		false
+	}
+}
+
+// the trace command, options are [hull <0-3>] [box <mins> <maxs>] [mask <name>]
+pub fn run(file: &BSPFile, start: Vector3, end: Vector3, options: &[String]) {
+	let mut hull: usize = 0;
+	let mut mins: Vector3 = Vector3::new();
+	let mut maxs: Vector3 = Vector3::new();
+	let mut mask: ContentsFlags = MASK_SOLID;
+	let (is_vbsp, is_quake): (bool, bool) = match file.header.bspver {
+		BSPVersion::VBSP => (true, false),
+		BSPVersion::Quake => (false, true),
+		_ => (false, false),
+	};
+
+	let mut i: usize = 0;
+	while i < options.len() {
+		let values: &[String] = &options[i + 1..];
+		let parse_f32 = |j: usize| values.get(j).and_then(|v| v.parse::<f32>().ok());
+		// source maps have no clipping hulls and quake and goldsrc maps have no brushes to sweep a box against
+		match (options[i].as_str(), is_vbsp) {
+			("hull", true) => {
+				println!("hull only works on quake and goldsrc maps! (use box for source maps)");
+				std::process::exit(0);
+			},
+			("box" | "mask", false) => {
+				println!("{} only works on source maps! (use hull for quake and goldsrc maps)", options[i]);
+				std::process::exit(0);
+			},
+			_ => {},
+		}
+		match options[i].as_str() {
+			"hull" => {
+				hull = match values.first().and_then(|v| v.parse::<usize>().ok()) {
+					Some(h) if h < 3 || (h == 3 && !is_quake) => h,
+					_ => {
+						println!("{}", if is_quake { "hull has to be 0, 1 or 2!" } else { "hull has to be 0, 1, 2 or 3!" });
+						std::process::exit(0);
+					},
+				};
+				i += 2;
+			},
+			"box" => {
+				let nums: Vec<f32> = (0..6).filter_map(parse_f32).collect();
+				if nums.len() != 6 {
+					println!("box needs 6 numbers (mins and maxs)!");
+					std::process::exit(0);
+				}
+				mins = Vector3 { x: nums[0], y: nums[1], z: nums[2] };
+				maxs = Vector3 { x: nums[3], y: nums[4], z: nums[5] };
+				i += 7;
+			},
+			"mask" => {
+				mask = match values.first().map(|v| v.as_str()) {
+					Some("solid") => MASK_SOLID,
+					Some("player") => MASK_PLAYERSOLID,
+					Some("npc") => MASK_NPCSOLID,
+					Some("shot") => MASK_SHOT,
+					Some("opaque") => MASK_OPAQUE,
+					Some("all") => MASK_ALL,
+					other => {
+						println!("unknown mask {}! (expected solid, player, npc, shot, opaque or all)", other.unwrap_or(""));
+						std::process::exit(0);
+					},
+				};
+				i += 2;
+			},
+			other => {
+				println!("unknown trace option {other}!");
+				std::process::exit(0);
+			},
+		}
+	}
+
+	println!("trace from {start} to {end}");
+	let trace: Trace = match &file.lump_data {
+		Lumps::VBSP(ld) => trace_vbsp(&VBsp::from_lumps(ld), 0, start, end, mins, maxs, mask),
+		_ => {
+			let bsp: Q1Bsp = Q1Bsp::from_file(file).unwrap();
+			let (hull_mins, hull_maxs) = if bsp.goldsrc { GOLDSRC_HULLS[hull] } else { QUAKE_HULLS[hull] };
+			println!("hull {hull} is a box from {hull_mins} to {hull_maxs}");
+			trace_q1(&bsp, 0, hull, start, end)
+		},
+	};
+	trace.print();
+}
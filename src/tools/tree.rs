@@ -0,0 +1,292 @@
+// exports the node tree of every model and prints some statistics about it
+// (depth, how balanced it is, leaf counts and sizes) so compiles can be compared
+
+use std::{fs, io::Write};
+use crate::{
+	file_structure::BSPFile,
+	lumps::lumptype::Lumps,
+	specific::vis::BitSet,
+	tools::{q1bsp::{self, Q1Bsp}, vbsp::VBsp},
+	utils::{bitflags_to_string, json_string, Vector3},
+};
+
+// the same tree for every engine
+struct TreeNode {
+	plane: usize,
+	normal: Vector3,
+	dist: f32,
+	mins: [i16; 3],
+	maxs: [i16; 3],
+	num_faces: usize,
+	children: [i32; 2], // negative numbers are -(leaf + 1)
+}
+
+struct TreeLeaf {
+	contents: String,
+	solid: bool,
+	mins: [i16; 3],
+	maxs: [i16; 3],
+	num_faces: usize, // leaffaces for source, marksurfaces for quake and goldsrc
+}
+
+struct Tree {
+	nodes: Vec<TreeNode>,
+	leaves: Vec<TreeLeaf>,
+	head_nodes: Vec<i32>, // one per model
+}
+
+#[derive(Default)]
+struct TreeStats {
+	nodes: usize,
+	// solid leaves are usually shared (quake puts every one of them in leaf 0)
+	// so depths are counted per path to a leaf and the rest per actual leaf
+	leaf_paths: usize,
+	leaves: usize,
+	solid_leaves: usize,
+	max_depth: usize,
+	min_depth: usize,
+	depth_sum: usize,
+	// for every node, leaves on the smaller side / leaves on the bigger side
+	balance_sum: f32,
+	volume_sum: f64,
+	empty_volume_sum: f64,
+}
+
+// a node or leaf waiting on the stack in Tree::walk
+struct WalkStep {
+	num: i32,
+	depth: usize,
+	parent: Option<(usize, usize)>, // parent node and which side of it this is on
+	expanded: bool, // children were pushed already, only the counts are left
+}
+
+fn from_vbsp(bsp: &VBsp) -> Tree {
+	Tree {
+		nodes: bsp.nodes.iter()
+			.map(|n| {
+				let plane = bsp.planes.get(n.plane_num as usize);
+				TreeNode {
+					plane: n.plane_num as usize,
+					normal: plane.map_or(Vector3::new(), |p| p.normal),
+					dist: plane.map_or(0.0, |p| p.dist),
+					mins: n.mins,
+					maxs: n.maxs,
+					num_faces: n.numfaces as usize,
+					children: n.children,
+				}
+			})
+			.collect(),
+		leaves: bsp.leaves.iter()
+			.map(|l| TreeLeaf {
+				contents: bitflags_to_string(l.contents.iter_names()),
+				solid: l.contents.contains(crate::flags::ContentsFlags::Solid),
+				mins: l.mins,
+				maxs: l.maxs,
+				num_faces: l.num_leaf_faces as usize,
+			})
+			.collect(),
+		head_nodes: bsp.models.iter().map(|m| m.head_node).collect(),
+	}
+}
+
+fn from_q1(bsp: &Q1Bsp) -> Tree {
+	Tree {
+		nodes: bsp.nodes.iter()
+			.map(|n| {
+				let plane = bsp.planes.get(n.plane);
+				TreeNode {
+					plane: n.plane,
+					normal: plane.map_or(Vector3::new(), |p| p.normal),
+					dist: plane.map_or(0.0, |p| p.dist),
+					mins: n.mins,
+					maxs: n.maxs,
+					num_faces: n.num_faces,
+					children: n.children,
+				}
+			})
+			.collect(),
+		leaves: bsp.leaves.iter()
+			.map(|l| TreeLeaf {
+				contents: q1bsp::contents_name(l.contents).to_string(),
+				solid: l.contents == q1bsp::CONTENTS_SOLID,
+				mins: l.mins,
+				maxs: l.maxs,
+				num_faces: l.num_marksurfaces,
+			})
+			.collect(),
+		head_nodes: bsp.models.iter().map(|m| m.headnodes[0]).collect(),
+	}
+}
+
+fn volume(mins: &[i16; 3], maxs: &[i16; 3]) -> f64 {
+	(0..3).map(|i| (maxs[i] as f64 - mins[i] as f64).max(0.0)).product()
+}
+
+impl Tree {
+	// walks the tree with its own stack so broken maps cant overflow the real one.
+	// every node is only walked once, so shared children and loops in broken trees are cut off
+	fn walk(&self, head: i32, stats: &mut TreeStats, leaves: &mut Vec<usize>) {
+		let mut visited: BitSet = BitSet::new(self.nodes.len());
+		// leaves under the front and back child of every node
+		let mut under: Vec<[usize; 2]> = vec![[0; 2]; self.nodes.len()];
+		let mut stack: Vec<WalkStep> = vec![WalkStep { num: head, depth: 0, parent: None, expanded: false }];
+		while let Some(WalkStep { num, depth, parent, expanded }) = stack.pop() {
+			if num < 0 {
+				stats.leaf_paths += 1;
+				stats.max_depth = stats.max_depth.max(depth);
+				stats.min_depth = if stats.leaf_paths == 1 { depth } else { stats.min_depth.min(depth) };
+				stats.depth_sum += depth;
+				leaves.push((-1 - num) as usize);
+				if let Some((p, side)) = parent { under[p][side] += 1; }
+				continue;
+			}
+			// broken indices are skipped
+			let Some(node) = self.nodes.get(num as usize) else { continue };
+			let index: usize = num as usize;
+
+			if expanded {
+				let [front, back] = under[index];
+				if front.max(back) > 0 {
+					stats.balance_sum += front.min(back) as f32 / front.max(back) as f32;
+				}
+				if let Some((p, side)) = parent { under[p][side] += front + back; }
+				continue;
+			}
+			if visited.get(index) { continue; }
+			visited.set(index);
+			stats.nodes += 1;
+			stack.push(WalkStep { num, depth, parent, expanded: true });
+			// back first so the front side is walked first
+			for side in [1, 0] {
+				stack.push(WalkStep { num: node.children[side], depth: depth + 1, parent: Some((index, side)), expanded: false });
+			}
+		}
+	}
+
+	fn stats(&self, model: usize) -> TreeStats {
+		let mut stats: TreeStats = TreeStats::default();
+		let mut leaves: Vec<usize> = vec![];
+		self.walk(self.head_nodes[model], &mut stats, &mut leaves);
+
+		leaves.sort();
+		leaves.dedup();
+		for leaf in leaves.iter().filter_map(|l| self.leaves.get(*l)) {
+			let v: f64 = volume(&leaf.mins, &leaf.maxs);
+			stats.leaves += 1;
+			stats.volume_sum += v;
+			if leaf.solid { stats.solid_leaves += 1; } else { stats.empty_volume_sum += v; }
+		}
+		stats
+	}
+}
+
+fn print_stats(model: usize, stats: &TreeStats) {
+	let leaves: f64 = stats.leaves.max(1) as f64;
+	let empty: usize = stats.leaves - stats.solid_leaves;
+	println!("model {model}:");
+	println!(
+		"\tnodes: {}, leaves: {} ({} solid, {} paths to a leaf)",
+		stats.nodes, stats.leaves, stats.solid_leaves, stats.leaf_paths,
+	);
+	println!(
+		"\tdepth: {} max, {} min, {:.2} average (a perfectly balanced tree would be {})",
+		stats.max_depth, stats.min_depth, stats.depth_sum as f64 / stats.leaf_paths.max(1) as f64,
+		(stats.leaf_paths as f64).log2().ceil(),
+	);
+	println!(
+		"\tbalance: {:.3} (1 is perfectly balanced)",
+		if stats.nodes > 0 { stats.balance_sum / stats.nodes as f32 } else { 1.0 },
+	);
+	println!(
+		"\taverage leaf volume: {:.0} ({:.0} for non solid leaves)",
+		stats.volume_sum / leaves,
+		if empty > 0 { stats.empty_volume_sum / empty as f64 } else { 0.0 },
+	);
+}
+
+fn to_dot(tree: &Tree) -> String {
+	let mut to_write: String = "digraph tree {\n".to_string();
+	let mut used_leaves: Vec<bool> = vec![false; tree.leaves.len()];
+	let child_name = |child: i32| if child < 0 { format!("leaf{}", -1 - child) } else { format!("node{child}") };
+
+	for (model, head) in tree.head_nodes.iter().enumerate() {
+		to_write.push_str(&format!("\tmodel{model} [shape=plaintext, label=\"model {model}\"];\n"));
+		to_write.push_str(&format!("\tmodel{model} -> {};\n", child_name(*head)));
+	}
+	for (i, node) in tree.nodes.iter().enumerate() {
+		to_write.push_str(&format!(
+			"\tnode{i} [shape=box, label=\"node {i}\\nplane {} {} {}\\nbounds {:?} {:?}\\n{} faces\"];\n",
+			node.plane, node.normal, node.dist, node.mins, node.maxs, node.num_faces,
+		));
+		for (side, child) in ["front", "back"].iter().zip(node.children) {
+			to_write.push_str(&format!("\tnode{i} -> {} [label=\"{side}\"];\n", child_name(child)));
+			if child < 0 {
+				if let Some(used) = used_leaves.get_mut((-1 - child) as usize) { *used = true; }
+			}
+		}
+	}
+	for (i, leaf) in tree.leaves.iter().enumerate().filter(|(i, _)| used_leaves[*i]) {
+		to_write.push_str(&format!(
+			"\tleaf{i} [label=\"leaf {i}\\n{}\\nbounds {:?} {:?}\\n{} faces\"{}];\n",
+			leaf.contents, leaf.mins, leaf.maxs, leaf.num_faces,
+			if leaf.solid { ", style=filled, fillcolor=gray" } else { "" },
+		));
+	}
+	to_write.push_str("}\n");
+	to_write
+}
+
+fn to_json(tree: &Tree) -> String {
+	let nodes: Vec<String> = tree.nodes.iter()
+		.map(|n| format!(
+			"{{\"plane\":{},\"normal\":[{},{},{}],\"dist\":{},\"mins\":{:?},\"maxs\":{:?},\"faces\":{},\"children\":{:?}}}",
+			n.plane, n.normal.x, n.normal.y, n.normal.z, n.dist, n.mins, n.maxs, n.num_faces, n.children,
+		))
+		.collect();
+	let leaves: Vec<String> = tree.leaves.iter()
+		.map(|l| format!(
+			"{{\"contents\":{},\"mins\":{:?},\"maxs\":{:?},\"faces\":{}}}",
+			json_string(&l.contents), l.mins, l.maxs, l.num_faces,
+		))
+		.collect();
+	let models: Vec<String> = tree.head_nodes.iter().enumerate()
+		.map(|(model, head)| {
+			let stats: TreeStats = tree.stats(model);
+			format!(
+				"{{\"head_node\":{head},\"nodes\":{},\"leaves\":{},\"solid_leaves\":{},\"leaf_paths\":{},\"max_depth\":{},\
+				\"min_depth\":{},\"average_depth\":{},\"balance\":{},\"average_leaf_volume\":{}}}",
+				stats.nodes, stats.leaves, stats.solid_leaves, stats.leaf_paths, stats.max_depth, stats.min_depth,
+				stats.depth_sum as f64 / stats.leaf_paths.max(1) as f64,
+				if stats.nodes > 0 { stats.balance_sum / stats.nodes as f32 } else { 1.0 },
+				stats.volume_sum / stats.leaves.max(1) as f64,
+			)
+		})
+		.collect();
+	format!(
+		"{{\"models\":[{}],\"nodes\":[{}],\"leaves\":[{}]}}\n",
+		models.join(","), nodes.join(","), leaves.join(","),
+	)
+}
+
+pub fn export(path: String, file: &BSPFile, format: &str) {
+	let tree: Tree = match &file.lump_data {
+		Lumps::VBSP(ld) => from_vbsp(&VBsp::from_lumps(ld)),
+		_ => from_q1(&Q1Bsp::from_file(file).unwrap()),
+	};
+	let to_write: String = match format {
+		"dot" => to_dot(&tree),
+		"json" => to_json(&tree),
+		_ => {
+			println!("unknown tree format {format}! (expected dot or json)");
+			std::process::exit(0);
+		},
+	};
+
+	for model in 0..tree.head_nodes.len() {
+		print_stats(model, &tree.stats(model));
+	}
+	let out_path: String = path.trim_end_matches(".bsp").to_owned() + "-tree." + format;
+	let mut out: fs::File = fs::File::create(&out_path).unwrap();
+	out.write_all(to_write.as_bytes()).unwrap();
+	println!("exported {} nodes and {} leaves to {out_path}", tree.nodes.len(), tree.leaves.len());
+}
@@ -0,0 +1,448 @@
+// structural checks for ci: every index from one lump into another has to be in range,
+// lump lengths have to be whole numbers of structs and lumps can't overlap or go past the end of the file.
+// the parser trusts all of this, so the header is checked first and the lumps are only parsed if it's fine
+
+use std::fs;
+use crate::{
+	file_structure::{BSPFile, BSPVersion, Header},
+	lumps::{lumptype::Lumps, vbsp::{self, VBSPLumpType}},
+	parse::{parse_file, parse_header},
+	reader::Reader,
+	tools::{areas, limits::struct_sizes, q1bsp::Q1Bsp, vbsp::VBsp},
+	GOLDSRC_MAGIC, QUAKE_MAGIC, VBSP_MAGIC,
+};
+
+// anything after this many messages of one kind is only counted
+const MAX_PRINTED: usize = 200;
+
+#[derive(Default)]
+struct Report {
+	errors: Vec<String>,
+	warnings: Vec<String>,
+}
+
+impl Report {
+	fn error(&mut self, msg: String) {
+		self.errors.push(msg);
+	}
+
+	fn warn(&mut self, msg: String) {
+		self.warnings.push(msg);
+	}
+
+	// index has to point at one of len elements
+	fn index(&mut self, what: &str, index: i64, len: usize, target: &str) {
+		if index < 0 || index as usize >= len {
+			self.error(format!("{what} points to {target} {index}, there are {len}"));
+		}
+	}
+
+	// first..first + count has to be inside len elements
+	fn range(&mut self, what: &str, first: i64, count: i64, len: usize, target: &str) {
+		if first < 0 || count < 0 || (first + count) as usize > len {
+			self.error(format!("{what} uses {target} {first} to {}, there are {len}", first + count));
+		}
+	}
+}
+
+// lumps that the limit tables don't have, with their struct size
+const VBSP_EXTRA_SIZES: [(&str, usize, usize); 8] = [
+	("lighting", 8, 4),
+	("hdr lighting", 53, 4),
+	("faceids", 11, 2),
+	("leafambientindex", 52, 4),
+	("hdr leafambientindex", 51, 4),
+	("leafambientlighting", 56, 28),
+	("hdr leafambientlighting", 55, 28),
+	("leafmindisttowater", 46, 2),
+];
+
+fn check_header(header: &Header, file_size: usize, sizes: &[(&str, usize, usize)], report: &mut Report) {
+	let (num_lumps, header_size): (usize, usize) = match header.bspver {
+		BSPVersion::VBSP => (64, 1036),
+		_ => (15, 124),
+	};
+	let mut used: Vec<(usize, usize, usize)> = vec![]; // start, end, lump
+	for (i, lump) in header.lumps.iter().enumerate().take(num_lumps) {
+		if lump.length == 0 { continue; }
+		let (start, end) = (lump.file_offset as usize, lump.file_offset as usize + lump.length as usize);
+		if end > file_size {
+			report.error(format!("lump {i} (bytes {start} to {end}) goes past the end of the file ({file_size} bytes)"));
+		}
+		if start < header_size {
+			report.error(format!("lump {i} starts at {start}, inside the header"));
+		}
+		if !start.is_multiple_of(4) {
+			report.warn(format!("lump {i} starts at {start}, which isn't 4 byte aligned"));
+		}
+		used.push((start, end, i));
+	}
+
+	used.sort();
+	for pair in used.windows(2) {
+		if pair[1].0 < pair[0].1 {
+			report.error(format!(
+				"lump {} (bytes {} to {}) overlaps lump {} (bytes {} to {})",
+				pair[0].2, pair[0].0, pair[0].1, pair[1].2, pair[1].0, pair[1].1,
+			));
+		}
+	}
+
+	for (name, lump, size) in sizes {
+		let length: usize = header.lumps[*lump].length as usize;
+		if !length.is_multiple_of(*size) {
+			report.error(format!("{name} lump (index {lump}) is {length} bytes, not a multiple of {size}"));
+		}
+	}
+}
+
+fn read_i32(bytes: &[u8], at: usize) -> Option<i32> {
+	Some(i32::from_le_bytes(bytes.get(at..at + 4)?.try_into().unwrap()))
+}
+
+// the game lump directory points at more data, which the parser also reads without checking.
+// the static prop dictionary and leaf list are checked too since their counts come from the file
+fn check_game_lumps(bytes: &[u8], header: &Header, report: &mut Report) {
+	let lump = header.lumps[35];
+	let (start, end): (usize, usize) = (lump.file_offset as usize, lump.file_offset as usize + lump.length as usize);
+	let Some(count) = read_i32(bytes, start).filter(|_| start + 4 <= end) else {
+		report.error(format!("game lump is {} bytes, too small for its lump count", lump.length));
+		return;
+	};
+	if count < 0 || start + 4 + count as usize * 16 > end {
+		report.error(format!("game lump directory has {count} entries, which don't fit in {} bytes", lump.length));
+		return;
+	}
+
+	for i in 0..count as usize {
+		let at: usize = start + 4 + i * 16;
+		let id: [u8; 4] = read_i32(bytes, at).unwrap().to_be_bytes();
+		let (offset, length): (i32, i32) = (read_i32(bytes, at + 8).unwrap(), read_i32(bytes, at + 12).unwrap());
+		let what: String = format!("game lump {i} ({})", String::from_utf8_lossy(&id));
+		if offset < 0 || length < 0 || offset as usize + length as usize > bytes.len() {
+			report.error(format!(
+				"{what} (bytes {offset} to {}) goes past the end of the file ({} bytes)",
+				offset as i64 + length as i64, bytes.len(),
+			));
+			continue;
+		}
+		if &id == b"sprp" { check_static_prop_lists(&bytes[offset as usize..offset as usize + length as usize], &what, report); }
+	}
+}
+
+// the dictionary (128 byte null terminated names) and the leaf list, followed by the entry count
+fn check_static_prop_lists(data: &[u8], what: &str, report: &mut Report) {
+	let Some(dict_entries) = read_i32(data, 0) else {
+		report.error(format!("{what} is too small for its dictionary count"));
+		return;
+	};
+	let mut at: usize = 4;
+	for i in 0..dict_entries.max(0) as usize {
+		let Some(name) = data.get(at..at + 128) else {
+			report.error(format!("{what} has {dict_entries} dictionary names, name {i} goes past the end of it"));
+			return;
+		};
+		let terminated: Option<&[u8]> = name.iter().position(|b| *b == 0).map(|n| &name[..n]);
+		if terminated.is_none_or(|n| std::str::from_utf8(n).is_err()) {
+			report.error(format!("{what} dictionary name {i} isn't a null terminated utf8 string"));
+			return;
+		}
+		at += 128;
+	}
+	let leaf_entries: i32 = read_i32(data, at).unwrap_or(-1);
+	if leaf_entries < 0 || at + 4 + leaf_entries as usize * 2 + 4 > data.len() {
+		report.error(format!("{what} leaf list and prop count don't fit after the dictionary"));
+	}
+}
+
+fn check_faces(name: &str, faces: &[vbsp::Face], bsp: &VBsp, ld: &[VBSPLumpType], report: &mut Report) {
+	let num_dispinfos: usize = if let VBSPLumpType::DispInfo(v) = &ld[26] { v.len() } else { 0 };
+	let num_orig_faces: usize = if let VBSPLumpType::OriginalFaces(v) = &ld[27] { v.len() } else { 0 };
+	let num_primitives: usize = if let VBSPLumpType::Primitives(v) = &ld[37] { v.len() } else { 0 };
+	for (i, face) in faces.iter().enumerate() {
+		let what: String = format!("{name} {i}");
+		report.index(&what, face.plane_num as i64, bsp.planes.len(), "plane");
+		report.range(&what, face.first_edge as i64, face.num_edges as i64, bsp.surfedges.len(), "surfedges");
+		report.index(&what, face.tex_info as i64, bsp.texinfos.len(), "texinfo");
+		if face.disp_info != -1 { report.index(&what, face.disp_info as i64, num_dispinfos, "dispinfo"); }
+		// original faces have -1 here, and the lump is empty in a lot of maps
+		if face.orig_face != -1 && num_orig_faces > 0 { report.index(&what, face.orig_face as i64, num_orig_faces, "original face"); }
+		if face.num_prims > 0 {
+			report.range(&what, face.first_prim_id as i64, face.num_prims as i64, num_primitives, "primitives");
+		}
+		if face.num_edges < 3 { report.warn(format!("{what} has {} edges", face.num_edges)); }
+	}
+}
+
+fn check_vbsp(file: &BSPFile, ld: &[VBSPLumpType], report: &mut Report) {
+	let bsp: VBsp = VBsp::from_lumps(ld);
+	let leaf_faces: &[u16] = if let VBSPLumpType::LeafFaces(v) = &ld[16] { v } else { &[] };
+	let dispinfos: &[vbsp::DispInfo] = if let VBSPLumpType::DispInfo(v) = &ld[26] { v } else { &[] };
+	let orig_faces: &[vbsp::Face] = if let VBSPLumpType::OriginalFaces(v) = &ld[27] { v } else { &[] };
+	let num_disp_verts: usize = if let VBSPLumpType::DispVerts(v) = &ld[33] { v.len() } else { 0 };
+	let num_disp_tris: usize = if let VBSPLumpType::DispTris(v) = &ld[48] { v.len() } else { 0 };
+	let primitives: &[vbsp::Primitive] = if let VBSPLumpType::Primitives(v) = &ld[37] { v } else { &[] };
+	let num_prim_verts: usize = if let VBSPLumpType::PrimVerts(v) = &ld[38] { v.len() } else { 0 };
+	let num_prim_indices: usize = if let VBSPLumpType::PrimIndices(v) = &ld[39] { v.len() } else { 0 };
+	let num_clusters: usize = bsp.vis.map_or(0, |v| v.num_clusters.max(0) as usize);
+	let string_data_size: usize = file.header.lumps[43].length as usize;
+
+	check_faces("face", bsp.faces, &bsp, ld, report);
+	check_faces("hdr face", bsp.faces_hdr, &bsp, ld, report);
+	check_faces("original face", orig_faces, &bsp, ld, report);
+	for (i, surfedge) in bsp.surfedges.iter().enumerate() {
+		report.index(&format!("surfedge {i}"), surfedge.unsigned_abs() as i64, bsp.edges.len(), "edge");
+	}
+	for (i, edge) in bsp.edges.iter().enumerate() {
+		for v in edge.pair { report.index(&format!("edge {i}"), v as i64, bsp.vertices.len(), "vertex"); }
+	}
+	for (i, texinfo) in bsp.texinfos.iter().enumerate() {
+		report.index(&format!("texinfo {i}"), texinfo.texdata as i64, bsp.texdata.len(), "texdata");
+	}
+	for (i, texdata) in bsp.texdata.iter().enumerate() {
+		let what: String = format!("texdata {i}");
+		report.index(&what, texdata.name_string_table_id as i64, bsp.texdata_string_table.len(), "string table entry");
+	}
+	for (i, entry) in bsp.texdata_string_table.iter().enumerate() {
+		if entry.offset as usize >= string_data_size {
+			report.error(format!("string table entry {i} points to byte {} of {string_data_size} in the string data", entry.offset));
+		}
+	}
+
+	for (i, node) in bsp.nodes.iter().enumerate() {
+		let what: String = format!("node {i}");
+		report.index(&what, node.plane_num as i64, bsp.planes.len(), "plane");
+		report.range(&what, node.first_face as i64, node.numfaces as i64, bsp.faces.len(), "faces");
+		for child in node.children {
+			if child >= 0 {
+				report.index(&what, child as i64, bsp.nodes.len(), "child node");
+			} else {
+				report.index(&what, (-1 - child) as i64, bsp.leaves.len(), "child leaf");
+			}
+		}
+	}
+	for (i, leaf) in bsp.leaves.iter().enumerate() {
+		let what: String = format!("leaf {i}");
+		report.range(&what, leaf.first_leaf_face as i64, leaf.num_leaf_faces as i64, leaf_faces.len(), "leaffaces");
+		report.range(&what, leaf.first_leaf_brushes as i64, leaf.num_leaf_brushes as i64, bsp.leaf_brushes.len(), "leafbrushes");
+		if leaf.cluster != -1 && bsp.vis.is_some() { report.index(&what, leaf.cluster as i64, num_clusters, "cluster"); }
+		let area: i64 = bsp.leaf_area(i).unwrap_or(0) as i64;
+		if !bsp.areas.is_empty() { report.index(&what, area, bsp.areas.len(), "area"); }
+	}
+	for (i, face) in leaf_faces.iter().enumerate() {
+		report.index(&format!("leafface {i}"), *face as i64, bsp.faces.len(), "face");
+	}
+	for (i, brush) in bsp.leaf_brushes.iter().enumerate() {
+		report.index(&format!("leafbrush {i}"), *brush as i64, bsp.brushes.len(), "brush");
+	}
+	for (i, brush) in bsp.brushes.iter().enumerate() {
+		report.range(&format!("brush {i}"), brush.first_side as i64, brush.num_sides as i64, bsp.brush_sides.len(), "brushsides");
+	}
+	for (i, side) in bsp.brush_sides.iter().enumerate() {
+		let what: String = format!("brushside {i}");
+		report.index(&what, side.plane_num as i64, bsp.planes.len(), "plane");
+		if side.texinfo != -1 { report.index(&what, side.texinfo as i64, bsp.texinfos.len(), "texinfo"); }
+		if side.dispinfo != -1 && side.dispinfo != 0 {
+			report.index(&what, side.dispinfo as i64, dispinfos.len(), "dispinfo");
+		}
+	}
+	for (i, model) in bsp.models.iter().enumerate() {
+		let what: String = format!("model {i}");
+		report.index(&what, model.head_node as i64, bsp.nodes.len(), "head node");
+		report.range(&what, model.first_face as i64, model.num_faces as i64, bsp.faces.len(), "faces");
+	}
+
+	for (i, area) in bsp.areas.iter().enumerate() {
+		report.range(&format!("area {i}"), area.first_area_portal as i64, area.num_area_portals as i64, bsp.area_portals.len(), "areaportals");
+	}
+	for (i, portal) in bsp.area_portals.iter().enumerate() {
+		let what: String = format!("areaportal {i}");
+		report.range(&what, portal.first_clip_portal_vert as i64, portal.clip_portal_verts as i64, bsp.clip_portal_verts.len(), "clipportalverts");
+		report.index(&what, portal.plane_num as i64, bsp.planes.len(), "plane");
+		report.index(&what, portal.other_area as i64, bsp.areas.len(), "area");
+	}
+
+	for (i, disp) in dispinfos.iter().enumerate() {
+		let what: String = format!("dispinfo {i}");
+		let side: i64 = (1 << disp.power.clamp(0, 8)) + 1;
+		report.index(&what, disp.map_face as i64, bsp.faces.len(), "face");
+		report.range(&what, disp.disp_vert_start as i64, side * side, num_disp_verts, "dispverts");
+		report.range(&what, disp.disp_tri_start as i64, (side - 1) * (side - 1) * 2, num_disp_tris, "disptris");
+		if !(2..=4).contains(&disp.power) { report.warn(format!("{what} has power {}", disp.power)); }
+	}
+	for (i, prim) in primitives.iter().enumerate() {
+		let what: String = format!("primitive {i}");
+		report.range(&what, prim.first_index as i64, prim.num_indices as i64, num_prim_indices, "primindices");
+		report.range(&what, prim.first_vertex as i64, prim.num_vertices as i64, num_prim_verts, "primverts");
+	}
+
+	for (i, overlay) in bsp.overlays.iter().enumerate() {
+		let what: String = format!("overlay {i} (id {})", overlay.id);
+		report.index(&what, overlay.texinfo as i64, bsp.texinfos.len(), "texinfo");
+		// the low 14 bits are the face count
+		let num_faces: usize = (overlay.face_count_and_render_order & 0x3FFF) as usize;
+		if num_faces > overlay.faces.len() {
+			report.error(format!("{what} has {num_faces} faces, the most there can be is {}", overlay.faces.len()));
+		}
+		for face in overlay.faces.iter().take(num_faces) {
+			report.index(&what, *face as i64, bsp.faces.len(), "face");
+		}
+	}
+
+	for (name, index, lighting) in [
+		("leafambientindex", bsp.ambient_index, bsp.ambient_lighting),
+		("hdr leafambientindex", bsp.ambient_index_hdr, bsp.ambient_lighting_hdr),
+	] {
+		if index.is_empty() { continue; }
+		if index.len() != bsp.leaves.len() {
+			report.error(format!("there are {} {name} entries but {} leaves", index.len(), bsp.leaves.len()));
+		}
+		for (i, entry) in index.iter().enumerate() {
+			let what: String = format!("{name} {i}");
+			report.range(&what, entry.first_ambient_sample as i64, entry.ambient_sample_count as i64, lighting.len(), "ambient samples");
+		}
+	}
+
+	if let Some(props) = bsp.static_props {
+		for (i, prop_type) in props.prop_types.iter().enumerate() {
+			report.index(&format!("static prop {i}"), *prop_type as i64, props.dict.names.len(), "model");
+		}
+		for (i, leaf) in props.leafs.leafs.iter().enumerate() {
+			report.index(&format!("static prop leaf entry {i}"), *leaf as i64, bsp.leaves.len(), "leaf");
+		}
+	}
+
+	// these walk the geometry, so only when the indices are fine
+	if !report.errors.is_empty() { return; }
+	for problem in bsp.lighting_problems() { report.error(problem); }
+	for problem in areas::build(&bsp).problems { report.warn(problem); }
+}
+
+fn check_q1(bsp: &Q1Bsp, report: &mut Report) {
+	for (i, face) in bsp.faces.iter().enumerate() {
+		let what: String = format!("face {i}");
+		report.index(&what, face.plane as i64, bsp.planes.len(), "plane");
+		report.range(&what, face.first_edge as i64, face.num_edges as i64, bsp.surfedges.len(), "surfedges");
+		report.index(&what, face.texinfo as i64, bsp.texinfos.len(), "texinfo");
+		if face.lightofs >= 0 && face.lightofs as usize >= bsp.lighting.len() {
+			report.error(format!("{what} lightmap starts at byte {} of {} in the lighting lump", face.lightofs, bsp.lighting.len()));
+		}
+		if face.num_edges < 3 { report.warn(format!("{what} has {} edges", face.num_edges)); }
+	}
+	for (i, surfedge) in bsp.surfedges.iter().enumerate() {
+		report.index(&format!("surfedge {i}"), surfedge.unsigned_abs() as i64, bsp.edges.len(), "edge");
+	}
+	for (i, edge) in bsp.edges.iter().enumerate() {
+		for v in edge { report.index(&format!("edge {i}"), *v as i64, bsp.vertices.len(), "vertex"); }
+	}
+	for (i, texinfo) in bsp.texinfos.iter().enumerate() {
+		report.index(&format!("texinfo {i}"), texinfo.miptex as i64, bsp.miptexs.len(), "miptex");
+	}
+	for (i, node) in bsp.nodes.iter().enumerate() {
+		let what: String = format!("node {i}");
+		report.index(&what, node.plane as i64, bsp.planes.len(), "plane");
+		report.range(&what, node.first_face as i64, node.num_faces as i64, bsp.faces.len(), "faces");
+		for child in node.children {
+			if child >= 0 {
+				report.index(&what, child as i64, bsp.nodes.len(), "child node");
+			} else {
+				report.index(&what, (-1 - child) as i64, bsp.leaves.len(), "child leaf");
+			}
+		}
+	}
+	for (i, clipnode) in bsp.clipnodes.iter().enumerate() {
+		let what: String = format!("clipnode {i}");
+		report.index(&what, clipnode.plane as i64, bsp.planes.len(), "plane");
+		for child in clipnode.children {
+			// negative children are contents, CONTENTS_EMPTY (-1) to CONTENTS_TRANSLUCENT (-15)
+			if child >= 0 {
+				report.index(&what, child as i64, bsp.clipnodes.len(), "child clipnode");
+			} else if child < -15 {
+				report.error(format!("{what} has child contents {child}"));
+			}
+		}
+	}
+	for (i, leaf) in bsp.leaves.iter().enumerate() {
+		let what: String = format!("leaf {i}");
+		report.range(&what, leaf.first_marksurface as i64, leaf.num_marksurfaces as i64, bsp.marksurfaces.len(), "marksurfaces");
+		if leaf.visofs >= 0 && leaf.visofs as usize >= bsp.visibility.len() {
+			report.error(format!("{what} vis data starts at byte {} of {}", leaf.visofs, bsp.visibility.len()));
+		}
+	}
+	for (i, face) in bsp.marksurfaces.iter().enumerate() {
+		report.index(&format!("marksurface {i}"), *face as i64, bsp.faces.len(), "face");
+	}
+	for (i, model) in bsp.models.iter().enumerate() {
+		let what: String = format!("model {i}");
+		report.index(&what, model.headnodes[0] as i64, bsp.nodes.len(), "head node");
+		// hull 0 is the node tree, the other 3 are clipnode trees (-1 if the hull is empty)
+		for head in &model.headnodes[1..] {
+			if *head >= 0 { report.index(&what, *head as i64, bsp.clipnodes.len(), "head clipnode"); }
+		}
+		report.range(&what, model.first_face as i64, model.num_faces as i64, bsp.faces.len(), "faces");
+	}
+}
+
+fn print_messages(name: &str, messages: &[String]) {
+	if messages.is_empty() { return; }
+	println!("\n{name} ({}):", messages.len());
+	for msg in messages.iter().take(MAX_PRINTED) { println!("\t{msg}"); }
+	if messages.len() > MAX_PRINTED { println!("\t... and {} more", messages.len() - MAX_PRINTED); }
+}
+
+// the exit code: 0 if everything is fine, 1 if there are only warnings, 2 if there are errors
+pub fn validate(path: &str) -> i32 {
+	let bytes: Vec<u8> = fs::read(path).unwrap_or_else(|e| {
+		println!("error while opening file: {e}");
+		std::process::exit(2);
+	});
+	let file_size: usize = bytes.len();
+	let mut report: Report = Report::default();
+
+	// parse_header doesn't check any of this either
+	let header_size: Option<usize> = match read_i32(&bytes, 0) {
+		Some(VBSP_MAGIC) => Some(1036),
+		Some(GOLDSRC_MAGIC) | Some(QUAKE_MAGIC) => Some(124),
+		_ => None,
+	};
+	match header_size {
+		None => report.error("not a source, goldsrc or quake bsp file".to_string()),
+		Some(size) if file_size < size => {
+			report.error(format!("file is {file_size} bytes, too small for its {size} byte header"));
+		},
+		Some(_) => {},
+	}
+	if !report.errors.is_empty() { return finish(&report); }
+
+	let mut reader: Reader = Reader::new(bytes);
+	let mut header: Header = Header::new();
+	parse_header(&mut reader, &mut header);
+	let mut sizes: Vec<(&str, usize, usize)> = struct_sizes(&header);
+	if let BSPVersion::VBSP = header.bspver { sizes.extend(VBSP_EXTRA_SIZES); }
+	check_header(&header, file_size, &sizes, &mut report);
+	// the game lumps are only worth looking at if the lump they're listed in is inside the file
+	if let BSPVersion::VBSP = header.bspver {
+		if report.errors.is_empty() { check_game_lumps(&reader.bytes, &header, &mut report); }
+	}
+
+	if report.errors.is_empty() {
+		reader.index = 0;
+		let file: BSPFile = parse_file(&mut reader);
+		match &file.lump_data {
+			Lumps::VBSP(ld) => check_vbsp(&file, ld, &mut report),
+			_ => check_q1(&Q1Bsp::from_file(&file).unwrap(), &mut report),
+		}
+	} else {
+		report.warn("the header is broken, lump contents weren't checked".to_string());
+	}
+
+	finish(&report)
+}
+
+// prints everything and returns the exit code
+fn finish(report: &Report) -> i32 {
+	print_messages("errors", &report.errors);
+	print_messages("warnings", &report.warnings);
+	println!("\n{} errors, {} warnings", report.errors.len(), report.warnings.len());
+	if !report.errors.is_empty() { 2 } else if !report.warnings.is_empty() { 1 } else { 0 }
+}
@@ -0,0 +1,263 @@
+// a borrowed view over the parsed source lumps so tools dont have to
+// match on the lump vector every time they need something
+
+use crate::{
+	flags::SurfaceFlags,
+	lumps::vbsp::{self, VBSPLumpType},
+	specific::gamelump,
+	utils::Vector3,
+};
+
+pub struct VBsp<'a> {
+	pub entities: &'a [Vec<(String, String)>],
+	pub planes: &'a [vbsp::Plane],
+	pub texdata: &'a [vbsp::TexData],
+	pub vertices: &'a [Vector3],
+	pub vis: Option<&'a vbsp::Vis>,
+	pub texinfos: &'a [vbsp::TexInfo],
+	pub nodes: &'a [vbsp::Node],
+	pub faces: &'a [vbsp::Face],
+	pub leaves: &'a [vbsp::Leaf],
+	pub lighting: &'a [vbsp::ColorRGBExp32],
+	pub lighting_hdr: &'a [vbsp::ColorRGBExp32],
+	pub faces_hdr: &'a [vbsp::Face],
+	pub edges: &'a [vbsp::Edge],
+	pub surfedges: &'a [i32],
+	pub models: &'a [vbsp::Model],
+	pub leaf_brushes: &'a [u16],
+	pub brushes: &'a [vbsp::Brush],
+	pub brush_sides: &'a [vbsp::BrushSide],
+	pub areas: &'a [vbsp::Area],
+	pub area_portals: &'a [vbsp::AreaPortal],
+	pub clip_portal_verts: &'a [vbsp::ClipPortalVert],
+	pub ambient_index: &'a [vbsp::LeafAmbientIndex],
+	pub ambient_index_hdr: &'a [vbsp::LeafAmbientIndex],
+	pub ambient_lighting: &'a [vbsp::LeafAmbientLighting],
+	pub ambient_lighting_hdr: &'a [vbsp::LeafAmbientLighting],
+	pub pakfile: &'a [u8],
+	pub texdata_strings: &'a [vbsp::TexDataStringData],
+	pub texdata_string_table: &'a [vbsp::TexDataStringTable],
+	pub overlays: &'a [vbsp::Overlay],
+	pub cubemaps: &'a [vbsp::CubemapSample],
+	pub static_props: Option<&'a gamelump::StaticProps>,
+}
+
+// texture and lightmap coordinates of a single face vertex
+#[derive(Debug, Clone, Copy)]
+pub struct FaceVertex {
+	pub pos: Vector3,
+	pub uv: [f32; 2], // normalized by the texdata size
+	pub luxel: [f32; 2], // relative to lightmap_texture_mins
+}
+
+impl<'a> VBsp<'a> {
+	pub fn from_lumps(ld: &'a [VBSPLumpType]) -> VBsp<'a> {
+		VBsp {
+			entities: if let VBSPLumpType::Entities(v) = &ld[0] { v } else { &[] },
+			planes: if let VBSPLumpType::Planes(v) = &ld[1] { v } else { &[] },
+			texdata: if let VBSPLumpType::TexData(v) = &ld[2] { v } else { &[] },
+			vertices: if let VBSPLumpType::Vertices(v) = &ld[3] { v } else { &[] },
+			vis: if let VBSPLumpType::Visibility(v) = &ld[4] { Some(v) } else { None },
+			texinfos: if let VBSPLumpType::TexInfo(v) = &ld[6] { v } else { &[] },
+			nodes: if let VBSPLumpType::Nodes(v) = &ld[5] { v } else { &[] },
+			faces: if let VBSPLumpType::Faces(v) = &ld[7] { v } else { &[] },
+			leaves: if let VBSPLumpType::Leaves(v) = &ld[10] { v } else { &[] },
+			lighting: if let VBSPLumpType::Lighting(v) = &ld[8] { v } else { &[] },
+			lighting_hdr: if let VBSPLumpType::LightingHDR(v) = &ld[53] { v } else { &[] },
+			faces_hdr: if let VBSPLumpType::FacesHDR(v) = &ld[58] { v } else { &[] },
+			edges: if let VBSPLumpType::Edges(v) = &ld[12] { v } else { &[] },
+			surfedges: if let VBSPLumpType::SurfEdges(v) = &ld[13] { v } else { &[] },
+			models: if let VBSPLumpType::Models(v) = &ld[14] { v } else { &[] },
+			leaf_brushes: if let VBSPLumpType::LeafBrushes(v) = &ld[17] { v } else { &[] },
+			brushes: if let VBSPLumpType::Brushes(v) = &ld[18] { v } else { &[] },
+			brush_sides: if let VBSPLumpType::BrushSides(v) = &ld[19] { v } else { &[] },
+			areas: if let VBSPLumpType::Areas(v) = &ld[20] { v } else { &[] },
+			area_portals: if let VBSPLumpType::AreaPortals(v) = &ld[21] { v } else { &[] },
+			clip_portal_verts: if let VBSPLumpType::ClipPortalVerts(v) = &ld[41] { v } else { &[] },
+			ambient_index: if let VBSPLumpType::LeafAmbientIndex(v) = &ld[52] { v } else { &[] },
+			ambient_index_hdr: if let VBSPLumpType::LeafAmbientIndexHDR(v) = &ld[51] { v } else { &[] },
+			ambient_lighting: if let VBSPLumpType::LeafAmbientLighting(v) = &ld[56] { v } else { &[] },
+			ambient_lighting_hdr: if let VBSPLumpType::LeafAmbientLightingHDR(v) = &ld[55] { v } else { &[] },
+			pakfile: if let VBSPLumpType::PakFile(v) = &ld[40] { &v.bytes } else { &[] },
+			texdata_strings: if let VBSPLumpType::TexDataStringData(v) = &ld[43] { v } else { &[] },
+			texdata_string_table: if let VBSPLumpType::TexDataStringTable(v) = &ld[44] { v } else { &[] },
+			overlays: if let VBSPLumpType::Overlays(v) = &ld[45] { v } else { &[] },
+			cubemaps: if let VBSPLumpType::Cubemaps(v) = &ld[42] { v } else { &[] },
+			static_props: if let VBSPLumpType::GameLump(v) = &ld[35] {
+				v.data.iter().map(|d| { let gamelump::GameLumpData::StaticProps(sprp) = d; sprp }).next()
+			} else { None },
+		}
+	}
+
+	// walks the tree of a model (0 is the world) down to the leaf the point is in
+	// broken node or plane indices (or a loop in the tree) end up in the solid leaf 0
+	pub fn leaf_at(&self, model: usize, point: &Vector3) -> usize {
+		let mut node: i32 = self.models.get(model).map_or(0, |m| m.head_node);
+		// a path down the tree can't pass more nodes than there are
+		for _ in 0..=self.nodes.len() {
+			if node < 0 { return (-1 - node) as usize; }
+			let Some(n) = self.nodes.get(node as usize) else { return 0 };
+			let Some(plane) = self.planes.get(n.plane_num as usize) else { return 0 };
+			let d: f32 = plane.normal.dot(point) - plane.dist;
+			node = n.children[if d >= 0.0 { 0 } else { 1 }];
+		}
+		0
+	}
+
+	// the material name of a texdata, through the string table
+	pub fn texdata_name(&self, texdata: usize) -> Option<&str> {
+		let id: usize = self.texdata.get(texdata)?.name_string_table_id as usize;
+		let offset: usize = self.texdata_string_table.get(id)?.offset as usize;
+		self.texdata_strings.iter()
+			.find(|s| s.offset == offset)
+			.map(|s| s.val.trim_end_matches('\0'))
+	}
+
+	// the area is the low 9 bits of area_flags
+	pub fn leaf_area(&self, leaf: usize) -> Option<u16> {
+		self.leaves.get(leaf).map(|l| (l.area_flags & ((1 << 9) - 1)) as u16)
+	}
+
+	pub fn face_vertices(&self, face: &vbsp::Face) -> Vec<Vector3> {
+		let mut res: Vec<Vector3> = vec![];
+		for i in 0..face.num_edges.max(0) as usize {
+			let Some(surfedge) = self.surfedges.get(face.first_edge as usize + i) else { break };
+			let Some(edge) = self.edges.get(surfedge.unsigned_abs() as usize) else { continue };
+			let vert: u16 = if *surfedge >= 0 { edge.pair[0] } else { edge.pair[1] };
+			let Some(vertex) = self.vertices.get(vert as usize) else { continue };
+			res.push(*vertex);
+		}
+		res
+	}
+
+	pub fn face_coords(&self, face: &vbsp::Face) -> Vec<FaceVertex> {
+		let Some(texinfo) = self.texinfos.get(face.tex_info as usize) else { return vec![] };
+		let (width, height): (f32, f32) = match self.texdata.get(texinfo.texdata as usize) {
+			Some(t) if t.width > 0 && t.height > 0 => (t.width as f32, t.height as f32),
+			_ => (1.0, 1.0),
+		};
+
+		self.face_vertices(face)
+		.into_iter()
+		.map(|pos| FaceVertex {
+			pos,
+			uv: [
+				project(&texinfo.texture_vecs[0], &pos) / width,
+				project(&texinfo.texture_vecs[1], &pos) / height,
+			],
+			luxel: [
+				project(&texinfo.lightmap_vecs[0], &pos) - face.lightmap_texture_mins[0] as f32,
+				project(&texinfo.lightmap_vecs[1], &pos) - face.lightmap_texture_mins[1] as f32,
+			],
+		})
+		.collect()
+	}
+
+	// lightmap mins and size the same way vbsp calculates them (CalcFaceExtents)
+	pub fn lightmap_extents(&self, face: &vbsp::Face) -> Option<([i32; 2], [i32; 2])> {
+		let texinfo: &vbsp::TexInfo = self.texinfos.get(face.tex_info as usize)?;
+		let verts: Vec<Vector3> = self.face_vertices(face);
+		if verts.is_empty() { return None; }
+
+		let mut mins: [i32; 2] = [0; 2];
+		let mut size: [i32; 2] = [0; 2];
+		for i in 0..2 {
+			let vals: Vec<f32> = verts.iter().map(|v| project(&texinfo.lightmap_vecs[i], v)).collect();
+			let min: f32 = vals.iter().cloned().fold(f32::MAX, f32::min).floor();
+			let max: f32 = vals.iter().cloned().fold(f32::MIN, f32::max).ceil();
+			mins[i] = min as i32;
+			size[i] = (max - min) as i32;
+		}
+		Some((mins, size))
+	}
+
+	// faces whose stored lightmap extents dont match the ones calculated from the geometry
+	pub fn lightmap_extent_problems(&self) -> Vec<(usize, String)> {
+		let mut res: Vec<(usize, String)> = vec![];
+		for (i, face) in self.faces.iter().enumerate() {
+			if face.light_offset < 0 || face.disp_info >= 0 { continue; }
+			let Some(texinfo) = self.texinfos.get(face.tex_info as usize) else { continue };
+			if texinfo.flags.intersects(SurfaceFlags::NoLight | SurfaceFlags::Sky | SurfaceFlags::Sky2D) { continue; }
+			let Some((mins, size)) = self.lightmap_extents(face) else { continue };
+			if mins != face.lightmap_texture_mins || size != face.lightmap_texture_size {
+				res.push((i, format!(
+					"lightmap extents are mins ({}, {}) size ({}, {}) but the geometry gives mins ({}, {}) size ({}, {})",
+					face.lightmap_texture_mins[0], face.lightmap_texture_mins[1],
+					face.lightmap_texture_size[0], face.lightmap_texture_size[1],
+					mins[0], mins[1], size[0], size[1],
+				)));
+			}
+		}
+		res
+	}
+
+	// the engine falls back to the ldr faces when the hdr face lump is empty
+	pub fn hdr_faces(&self) -> &[vbsp::Face] {
+		if self.faces_hdr.is_empty() { self.faces } else { self.faces_hdr }
+	}
+
+	// number of ColorRGBExp32 samples the face has in the lighting lump
+	pub fn lightmap_sample_count(&self, face: &vbsp::Face) -> usize {
+		let bumped: bool = self.texinfos.get(face.tex_info as usize)
+			.is_some_and(|t| t.flags.contains(SurfaceFlags::BumpLight));
+		let styles: usize = face.styles.iter().take_while(|s| **s != 255).count();
+		let luxels: usize = (face.lightmap_texture_size[0] + 1).max(0) as usize
+			* (face.lightmap_texture_size[1] + 1).max(0) as usize;
+		styles * if bumped { 4 } else { 1 } * luxels
+	}
+
+	// checks that both face lists point inside their lighting lumps and that
+	// the hdr faces are the same faces as the ldr ones
+	pub fn lighting_problems(&self) -> Vec<String> {
+		let mut res: Vec<String> = vec![];
+		let mut check_offsets = |name: &str, faces: &[vbsp::Face], lighting: &[vbsp::ColorRGBExp32]| {
+			for (i, face) in faces.iter().enumerate() {
+				if face.light_offset < 0 { continue; }
+				let end: usize = face.light_offset as usize / 4 + self.lightmap_sample_count(face);
+				if face.light_offset % 4 != 0 || end > lighting.len() {
+					res.push(format!(
+						"{name} face {i} lightmap ({} to {} bytes) is outside of the lighting lump ({} bytes)",
+						face.light_offset, end * 4, lighting.len() * 4,
+					));
+				}
+			}
+		};
+		if !self.lighting.is_empty() { check_offsets("ldr", self.faces, self.lighting); }
+		if !self.lighting_hdr.is_empty() { check_offsets("hdr", self.hdr_faces(), self.lighting_hdr); }
+
+		if self.faces_hdr.is_empty() { return res; }
+		if self.faces_hdr.len() != self.faces.len() {
+			res.push(format!(
+				"there are {} hdr faces but {} ldr faces",
+				self.faces_hdr.len(), self.faces.len(),
+			));
+		}
+		if self.lighting_hdr.is_empty() {
+			res.push("there are hdr faces but no hdr lighting".to_string());
+		}
+		for (i, (ldr, hdr)) in self.faces.iter().zip(self.faces_hdr).enumerate() {
+			let same: bool = ldr.plane_num == hdr.plane_num
+				&& ldr.side == hdr.side
+				&& ldr.first_edge == hdr.first_edge
+				&& ldr.num_edges == hdr.num_edges
+				&& ldr.tex_info == hdr.tex_info
+				&& ldr.disp_info == hdr.disp_info
+				&& ldr.lightmap_texture_mins == hdr.lightmap_texture_mins
+				&& ldr.lightmap_texture_size == hdr.lightmap_texture_size;
+			if !same {
+				res.push(format!("hdr face {i} does not match ldr face {i}"));
+			} else if ldr.styles != hdr.styles {
+				res.push(format!(
+					"hdr face {i} has styles {:?} but ldr face {i} has {:?}",
+					hdr.styles, ldr.styles,
+				));
+			}
+		}
+		res
+	}
+}
+
+// texture/lightmap vecs are [x, y, z, offset]
+fn project(vec: &[f32; 4], pos: &Vector3) -> f32 {
+	vec[0] * pos.x + vec[1] * pos.y + vec[2] * pos.z + vec[3]
+}
@@ -0,0 +1,165 @@
+// pvs/pas queries between points in the map, and which entities are potentially visible from a point
+// (the engine only networks entities that are in the pvs of the player)
+
+use crate::{
+	file_structure::BSPFile,
+	lumps::lumptype::Lumps,
+	specific::vis::BitSet,
+	tools::{q1bsp::Q1Bsp, vbsp::VBsp},
+	utils::{entity_value, parse_vector, Vector3},
+};
+
+enum Map<'a> {
+	Source(VBsp<'a>),
+	// no clusters here, every visleaf is a cluster (cluster = leaf - 1)
+	Quake(Q1Bsp),
+}
+
+impl Map<'_> {
+	fn num_clusters(&self) -> usize {
+		match self {
+			Map::Source(bsp) => bsp.vis.map_or(0, |v| v.num_clusters.max(0) as usize),
+			Map::Quake(bsp) => bsp.num_visleafs(),
+		}
+	}
+
+	// (leaf, cluster), the cluster is -1 in solid leaves
+	fn locate(&self, point: &Vector3) -> (usize, i32) {
+		match self {
+			Map::Source(bsp) => {
+				let leaf: usize = bsp.leaf_at(0, point);
+				(leaf, bsp.leaves.get(leaf).map_or(-1, |l| l.cluster as i32))
+			},
+			Map::Quake(bsp) => {
+				let leaf: usize = bsp.leaf_at(0, point).unwrap_or(0);
+				(leaf, if leaf == 0 || leaf > bsp.num_visleafs() { -1 } else { leaf as i32 - 1 })
+			},
+		}
+	}
+
+	fn pvs(&self, cluster: i32) -> BitSet {
+		if cluster < 0 { return BitSet::new(self.num_clusters()); }
+		match self {
+			Map::Source(bsp) => bsp.vis
+				.map(|v| v.pvs(cluster))
+				.unwrap_or(BitSet::new(self.num_clusters())),
+			Map::Quake(bsp) => bsp.leaf_pvs(cluster as usize + 1),
+		}
+	}
+
+	// quake and goldsrc dont store the pas, the engine builds it at load time
+	// out of everything visible from anything visible (SV_CalcPHS)
+	fn pas(&self, cluster: i32) -> BitSet {
+		if cluster < 0 { return BitSet::new(self.num_clusters()); }
+		match self {
+			Map::Source(bsp) => bsp.vis
+				.map(|v| v.pas(cluster))
+				.unwrap_or(BitSet::new(self.num_clusters())),
+			Map::Quake(bsp) => {
+				let mut res: BitSet = BitSet::new(self.num_clusters());
+				for visible in self.pvs(cluster).ones() {
+					for audible in bsp.leaf_pvs(visible + 1).ones() { res.set(audible); }
+				}
+				res
+			},
+		}
+	}
+
+	// single queries only decode the one row they need
+	fn can_see(&self, from: i32, to: i32) -> bool {
+		match self {
+			Map::Source(bsp) => bsp.vis.is_some_and(|v| v.can_see(from, to)),
+			Map::Quake(bsp) => from >= 0 && to >= 0 && bsp.leaf_can_see(from as usize + 1, to as usize + 1),
+		}
+	}
+
+	fn can_hear(&self, from: i32, to: i32) -> bool {
+		match self {
+			Map::Source(bsp) => bsp.vis.is_some_and(|v| v.can_hear(from, to)),
+			Map::Quake(_) => to >= 0 && self.pas(from).get(to as usize),
+		}
+	}
+
+	fn entities(&self) -> &[Vec<(String, String)>] {
+		match self {
+			Map::Source(bsp) => bsp.entities,
+			Map::Quake(bsp) => &bsp.entities,
+		}
+	}
+
+	// the middle of a brush model, for brush entities without an origin
+	fn model_center(&self, model: usize) -> Option<Vector3> {
+		let (mins, maxs) = match self {
+			Map::Source(bsp) => bsp.models.get(model).map(|m| (m.mins, m.maxs))?,
+			Map::Quake(bsp) => bsp.models.get(model).map(|m| (m.mins, m.maxs))?,
+		};
+		Some((mins + maxs) * 0.5)
+	}
+}
+
+fn map_from_file(file: &BSPFile) -> Map<'_> {
+	match &file.lump_data {
+		Lumps::VBSP(ld) => Map::Source(VBsp::from_lumps(ld)),
+		_ => Map::Quake(Q1Bsp::from_file(file).unwrap()),
+	}
+}
+
+fn print_location(map: &Map, point: &Vector3) -> i32 {
+	let (leaf, cluster) = map.locate(point);
+	println!("point {point} is in leaf {leaf}, cluster {cluster}");
+	cluster
+}
+
+// whether two points can potentially see and hear each other
+pub fn between(file: &BSPFile, a: Vector3, b: Vector3) {
+	let map: Map = map_from_file(file);
+	let cluster_a: i32 = print_location(&map, &a);
+	let cluster_b: i32 = print_location(&map, &b);
+	if cluster_a < 0 || cluster_b < 0 {
+		println!("one of the points is outside of the map (or in a solid leaf)");
+		return;
+	}
+
+	let yes_no = |v: bool| if v { "yes" } else { "no" };
+	println!("a can see b (pvs): {}", yes_no(map.can_see(cluster_a, cluster_b)));
+	println!("b can see a (pvs): {}", yes_no(map.can_see(cluster_b, cluster_a)));
+	println!("a can hear b (pas): {}", yes_no(map.can_hear(cluster_a, cluster_b)));
+	println!("b can hear a (pas): {}", yes_no(map.can_hear(cluster_b, cluster_a)));
+}
+
+// every entity whose origin is in a cluster that is potentially visible from the point
+pub fn entities_from(file: &BSPFile, point: Vector3) {
+	let map: Map = map_from_file(file);
+	let cluster: i32 = print_location(&map, &point);
+	let pvs: BitSet = map.pvs(cluster);
+	let pas: BitSet = map.pas(cluster);
+	println!("{} visible and {} audible clusters", pvs.count_ones(), pas.count_ones());
+
+	let mut visible: Vec<String> = vec![];
+	let mut skipped: usize = 0;
+	for (i, ent) in map.entities().iter().enumerate() {
+		let classname: &str = entity_value(ent, "classname").unwrap_or("");
+		if classname == "worldspawn" { continue; }
+		let origin: Option<Vector3> = entity_value(ent, "origin")
+			.and_then(parse_vector)
+			.or_else(|| entity_value(ent, "model")
+				.and_then(|m| m.strip_prefix('*'))
+				.and_then(|m| m.parse::<usize>().ok())
+				.and_then(|m| map.model_center(m)));
+		let Some(origin) = origin else {
+			skipped += 1;
+			continue;
+		};
+
+		let (_, ent_cluster) = map.locate(&origin);
+		if ent_cluster < 0 || !pvs.get(ent_cluster as usize) { continue; }
+		visible.push(format!(
+			"\t[ent{i}] {classname}{} at {origin}, cluster {ent_cluster}",
+			entity_value(ent, "targetname").map_or("".to_string(), |t| format!(" \"{t}\"")),
+		));
+	}
+
+	println!("{} visible entities:", visible.len());
+	for line in visible { println!("{line}"); }
+	if skipped > 0 { println!("skipped {skipped} entities without an origin"); }
+}
@@ -0,0 +1,402 @@
+// valve texture format, versions 7.0 to 7.5.
+// maps pack their cubemaps (and sometimes custom textures) as vtfs in the pakfile,
+// this reads the header and resources and decodes the biggest mip of the common formats to rgba
+
+use std::{fs, path::Path};
+use crate::{
+	flags::VtfFlags,
+	image::write_png,
+	lumps::vbsp::VBSPLumpType,
+	tools::{content::Pak, vbsp::VBsp},
+	utils::{bitflags_to_string, is_safe_path, Vector3},
+};
+
+// IMAGE_FORMAT names and bytes per pixel (0 for the block compressed ones)
+const FORMATS: [(&str, usize); 27] = [
+	("RGBA8888", 4), ("ABGR8888", 4), ("RGB888", 3), ("BGR888", 3), ("RGB565", 2),
+	("I8", 1), ("IA88", 2), ("P8", 1), ("A8", 1), ("RGB888_BLUESCREEN", 3),
+	("BGR888_BLUESCREEN", 3), ("ARGB8888", 4), ("BGRA8888", 4), ("DXT1", 0), ("DXT3", 0),
+	("DXT5", 0), ("BGRX8888", 4), ("BGR565", 2), ("BGRX5551", 2), ("BGRA4444", 2),
+	("DXT1_ONEBITALPHA", 0), ("BGRA5551", 2), ("UV88", 2), ("UVWQ8888", 4), ("RGBA16161616F", 8),
+	("RGBA16161616", 8), ("UVLX8888", 4),
+];
+
+const FORMAT_DXT1: i32 = 13;
+const FORMAT_DXT3: i32 = 14;
+const FORMAT_DXT5: i32 = 15;
+const FORMAT_DXT1_ONEBITALPHA: i32 = 20;
+
+// 7.3+ resource tags
+const RESOURCE_LOW_RES: [u8; 3] = [0x01, 0, 0];
+const RESOURCE_HIGH_RES: [u8; 3] = [0x30, 0, 0];
+
+pub fn format_name(format: i32) -> &'static str {
+	if format == -1 { return "none"; }
+	FORMATS.get(format as usize).map_or("unknown", |f| f.0)
+}
+
+// bytes of one image (one face of one frame of one mip level)
+pub fn image_size(format: i32, width: usize, height: usize) -> usize {
+	if width == 0 || height == 0 { return 0; }
+	let blocks: usize = width.div_ceil(4).max(1) * height.div_ceil(4).max(1);
+	match format {
+		-1 => 0,
+		FORMAT_DXT1 | FORMAT_DXT1_ONEBITALPHA => blocks * 8,
+		FORMAT_DXT3 | FORMAT_DXT5 => blocks * 16,
+		_ => width * height * FORMATS.get(format as usize).map_or(0, |f| f.1),
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VtfResource {
+	pub tag: [u8; 3],
+	pub data: u32, // an offset, or the value itself for the small ones (crc, lod, tso)
+}
+
+impl VtfResource {
+	fn describe(&self) -> String {
+		match &self.tag {
+			&RESOURCE_LOW_RES => "low res image".to_string(),
+			&RESOURCE_HIGH_RES => "high res image".to_string(),
+			[0x10, 0, 0] => "particle sheet".to_string(),
+			b"CRC" => format!("crc {:#010x}", self.data),
+			b"LOD" => format!("lod control u {} v {}", self.data & 0xFF, (self.data >> 8) & 0xFF),
+			b"TSO" => format!("extended flags {:#x}", self.data),
+			b"KVD" => "keyvalues".to_string(),
+			tag => format!("unknown resource {tag:?}"),
+		}
+	}
+}
+
+#[allow(unused)]
+pub struct Vtf<'a> {
+	pub version: [u32; 2],
+	pub header_size: u32,
+	pub width: u16,
+	pub height: u16,
+	pub flags: VtfFlags,
+	pub frames: u16,
+	pub first_frame: u16,
+	pub reflectivity: Vector3,
+	pub bumpmap_scale: f32,
+	pub format: i32,
+	pub mip_count: u8,
+	pub low_res_format: i32,
+	pub low_res_width: u8,
+	pub low_res_height: u8,
+	pub depth: u16, // 7.2+, 1 before that
+	pub resources: Vec<VtfResource>, // 7.3+
+	bytes: &'a [u8],
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+	Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().unwrap()))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+	Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().unwrap()))
+}
+
+fn read_f32(bytes: &[u8], at: usize) -> Option<f32> {
+	read_u32(bytes, at).map(f32::from_bits)
+}
+
+impl Vtf<'_> {
+	pub fn parse(bytes: &[u8]) -> Result<Vtf<'_>, String> {
+		if bytes.get(0..4) != Some(b"VTF\0") { return Err("not a vtf file".to_string()); }
+		let cut = || "header is cut off".to_string();
+		let version: [u32; 2] = [read_u32(bytes, 4).ok_or_else(cut)?, read_u32(bytes, 8).ok_or_else(cut)?];
+		if version[0] != 7 || version[1] > 5 {
+			return Err(format!("unsupported version {}.{}", version[0], version[1]));
+		}
+
+		let mut vtf: Vtf = Vtf {
+			version,
+			header_size: read_u32(bytes, 12).ok_or_else(cut)?,
+			width: read_u16(bytes, 16).ok_or_else(cut)?,
+			height: read_u16(bytes, 18).ok_or_else(cut)?,
+			flags: VtfFlags::from_bits_retain(read_u32(bytes, 20).ok_or_else(cut)?),
+			frames: read_u16(bytes, 24).ok_or_else(cut)?,
+			first_frame: read_u16(bytes, 26).ok_or_else(cut)?,
+			reflectivity: Vector3 {
+				x: read_f32(bytes, 32).ok_or_else(cut)?,
+				y: read_f32(bytes, 36).ok_or_else(cut)?,
+				z: read_f32(bytes, 40).ok_or_else(cut)?,
+			},
+			bumpmap_scale: read_f32(bytes, 48).ok_or_else(cut)?,
+			format: read_u32(bytes, 52).ok_or_else(cut)? as i32,
+			mip_count: *bytes.get(56).ok_or_else(cut)?,
+			low_res_format: read_u32(bytes, 57).ok_or_else(cut)? as i32,
+			low_res_width: *bytes.get(61).ok_or_else(cut)?,
+			low_res_height: *bytes.get(62).ok_or_else(cut)?,
+			depth: 1,
+			resources: vec![],
+			bytes,
+		};
+		if vtf.width == 0 || vtf.height == 0 {
+			return Err(format!("image is {}x{}", vtf.width, vtf.height));
+		}
+		if version[1] >= 2 { vtf.depth = read_u16(bytes, 63).ok_or_else(cut)?.max(1); }
+		if version[1] >= 3 {
+			let num_resources: usize = read_u32(bytes, 68).ok_or_else(cut)? as usize;
+			for i in 0..num_resources {
+				let at: usize = 80 + i * 8;
+				let entry: &[u8] = bytes.get(at..at + 8).ok_or(format!("resource {i} is cut off"))?;
+				vtf.resources.push(VtfResource {
+					tag: [entry[0], entry[1], entry[2]],
+					data: read_u32(entry, 4).unwrap(),
+				});
+			}
+		}
+		Ok(vtf)
+	}
+
+	// cubemaps before 7.5 have a 7th spheremap face, unless first_frame is -1
+	pub fn faces(&self) -> usize {
+		if !self.flags.contains(VtfFlags::EnvMap) { return 1; }
+		if self.version[1] < 5 && self.first_frame != 0xFFFF { 7 } else { 6 }
+	}
+
+	fn mip_size(&self, level: usize) -> (usize, usize, usize) {
+		(
+			(self.width as usize >> level).max(1),
+			(self.height as usize >> level).max(1),
+			(self.depth as usize >> level).max(1),
+		)
+	}
+
+	// the high res data has every mip from the smallest to the biggest,
+	// and every frame, face and depth slice inside each mip
+	fn top_mip_offset(&self) -> Option<usize> {
+		let start: usize = match self.resources.iter().find(|r| r.tag == RESOURCE_HIGH_RES) {
+			Some(r) => r.data as usize,
+			None if self.version[1] >= 3 => return None,
+			None => self.header_size as usize + image_size(
+				self.low_res_format, self.low_res_width as usize, self.low_res_height as usize,
+			),
+		};
+		let smaller_mips: usize = (1..self.mip_count.max(1) as usize)
+			.map(|level| {
+				let (w, h, d) = self.mip_size(level);
+				image_size(self.format, w, h) * d * self.faces() * self.frames.max(1) as usize
+			})
+			.sum();
+		Some(start + smaller_mips)
+	}
+
+	// rgba of the first frame of the biggest mip, cubemap faces are put next to each other
+	pub fn top_mip_rgba(&self) -> Result<(usize, usize, Vec<u8>), String> {
+		let (width, height) = (self.width as usize, self.height as usize);
+		let size: usize = image_size(self.format, width, height);
+		let start: usize = self.top_mip_offset().ok_or("no high res image resource".to_string())?;
+		// the spheremap face isnt interesting
+		let faces: usize = self.faces().min(6);
+
+		let mut res: Vec<u8> = vec![0; width * faces * height * 4];
+		for face in 0..faces {
+			let at: usize = start + face * size;
+			let data: &[u8] = self.bytes.get(at..at + size).ok_or("image data goes past the end of the file".to_string())?;
+			let rgba: Vec<u8> = decode(self.format, width, height, data)
+				.ok_or(format!("{} textures can't be decoded", format_name(self.format)))?;
+			for (y, row) in rgba.chunks(width * 4).enumerate() {
+				let out: usize = (y * width * faces + face * width) * 4;
+				res[out..out + width * 4].copy_from_slice(row);
+			}
+		}
+		Ok((width * faces, height, res))
+	}
+
+	pub fn info(&self) -> String {
+		let mut res: String = format!(
+			"\tversion {}.{}, {}x{}, {} frames, {} faces, depth {}\n",
+			self.version[0], self.version[1], self.width, self.height, self.frames, self.faces(), self.depth,
+		);
+		res.push_str(&format!(
+			"\tformat: {}, {} mips, low res: {} {}x{}\n",
+			format_name(self.format), self.mip_count,
+			format_name(self.low_res_format), self.low_res_width, self.low_res_height,
+		));
+		res.push_str(&format!("\tflags: {}\n", bitflags_to_string(self.flags.iter_names())));
+		res.push_str(&format!("\treflectivity: {}, bumpmap scale: {}\n", self.reflectivity, self.bumpmap_scale));
+		if !self.resources.is_empty() {
+			res.push_str(&format!(
+				"\tresources: {}\n",
+				self.resources.iter().map(|r| r.describe()).collect::<Vec<String>>().join(", "),
+			));
+		}
+		res
+	}
+}
+
+fn rgb565(c: u16) -> [u8; 3] {
+	let (r, g, b) = ((c >> 11) & 31, (c >> 5) & 63, c & 31);
+	[(r * 255 / 31) as u8, (g * 255 / 63) as u8, (b * 255 / 31) as u8]
+}
+
+// the 4 colors of a dxt block and which one each pixel uses
+fn dxt_colors(block: &[u8], allow_alpha: bool) -> [[u8; 4]; 16] {
+	let (c0, c1) = (u16::from_le_bytes([block[0], block[1]]), u16::from_le_bytes([block[2], block[3]]));
+	let (a, b) = (rgb565(c0), rgb565(c1));
+	let mix = |wa: u16, wb: u16| -> [u8; 4] {
+		let ch = |i: usize| ((a[i] as u16 * wa + b[i] as u16 * wb) / (wa + wb)) as u8;
+		[ch(0), ch(1), ch(2), 255]
+	};
+	// dxt1 with c0 <= c1 has a transparent black color instead of the second blend
+	let colors: [[u8; 4]; 4] = if c0 > c1 || !allow_alpha {
+		[[a[0], a[1], a[2], 255], [b[0], b[1], b[2], 255], mix(2, 1), mix(1, 2)]
+	} else {
+		[[a[0], a[1], a[2], 255], [b[0], b[1], b[2], 255], mix(1, 1), [0, 0, 0, 0]]
+	};
+	let indices: u32 = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+	let mut res: [[u8; 4]; 16] = [[0; 4]; 16];
+	for (i, pixel) in res.iter_mut().enumerate() {
+		*pixel = colors[(indices >> (i * 2)) as usize & 3];
+	}
+	res
+}
+
+// interpolated dxt5 alpha
+fn dxt5_alpha(block: &[u8]) -> [u8; 16] {
+	let (a0, a1) = (block[0] as u32, block[1] as u32);
+	let mut alphas: [u8; 8] = [a0 as u8, a1 as u8, 0, 0, 0, 0, 0, 255];
+	if a0 > a1 {
+		for i in 1..7 { alphas[i + 1] = (((7 - i as u32) * a0 + i as u32 * a1) / 7) as u8; }
+	} else {
+		for i in 1..5 { alphas[i + 1] = (((5 - i as u32) * a0 + i as u32 * a1) / 5) as u8; }
+	}
+	let bits: u64 = block[2..8].iter().rev().fold(0, |acc, b| (acc << 8) | *b as u64);
+	let mut res: [u8; 16] = [0; 16];
+	for (i, alpha) in res.iter_mut().enumerate() {
+		*alpha = alphas[(bits >> (i * 3)) as usize & 7];
+	}
+	res
+}
+
+fn decode_dxt(format: i32, width: usize, height: usize, data: &[u8]) -> Vec<u8> {
+	let block_size: usize = if format == FORMAT_DXT1 || format == FORMAT_DXT1_ONEBITALPHA { 8 } else { 16 };
+	let blocks_wide: usize = width.div_ceil(4).max(1);
+	let mut res: Vec<u8> = vec![0; width * height * 4];
+	for (i, block) in data.chunks_exact(block_size).enumerate() {
+		let (bx, by) = ((i % blocks_wide) * 4, (i / blocks_wide) * 4);
+		let mut pixels: [[u8; 4]; 16] = match format {
+			FORMAT_DXT1 | FORMAT_DXT1_ONEBITALPHA => dxt_colors(block, true),
+			_ => dxt_colors(&block[8..], false),
+		};
+		match format {
+			// 4 bit explicit alpha
+			FORMAT_DXT3 => for (p, pixel) in pixels.iter_mut().enumerate() {
+				pixel[3] = ((block[p / 2] >> ((p % 2) * 4)) & 15) * 17;
+			},
+			FORMAT_DXT5 => for (pixel, alpha) in pixels.iter_mut().zip(dxt5_alpha(block)) {
+				pixel[3] = alpha;
+			},
+			_ => {},
+		}
+		for (p, pixel) in pixels.iter().enumerate() {
+			let (x, y) = (bx + p % 4, by + p / 4);
+			if x < width && y < height {
+				res[(y * width + x) * 4..(y * width + x) * 4 + 4].copy_from_slice(pixel);
+			}
+		}
+	}
+	res
+}
+
+fn half_to_f32(h: u16) -> f32 {
+	let sign: f32 = if h & 0x8000 != 0 { -1.0 } else { 1.0 };
+	let exp: i32 = ((h >> 10) & 31) as i32;
+	let mantissa: f32 = (h & 1023) as f32;
+	match exp {
+		0 => sign * mantissa * 2f32.powi(-24),
+		31 => if mantissa == 0.0 { sign * f32::INFINITY } else { f32::NAN },
+		_ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exp - 15),
+	}
+}
+
+// none for formats that aren't supported
+fn decode(format: i32, width: usize, height: usize, data: &[u8]) -> Option<Vec<u8>> {
+	if matches!(format, FORMAT_DXT1 | FORMAT_DXT3 | FORMAT_DXT5 | FORMAT_DXT1_ONEBITALPHA) {
+		return Some(decode_dxt(format, width, height, data));
+	}
+	let bpp: usize = FORMATS.get(format as usize)?.1;
+	let mut res: Vec<u8> = Vec::with_capacity(width * height * 4);
+	for p in data.chunks_exact(bpp).take(width * height) {
+		let pixel: [u8; 4] = match format {
+			0 => [p[0], p[1], p[2], p[3]], // RGBA8888
+			1 => [p[3], p[2], p[1], p[0]], // ABGR8888
+			2 => [p[0], p[1], p[2], 255], // RGB888
+			3 => [p[2], p[1], p[0], 255], // BGR888
+			5 => [p[0], p[0], p[0], 255], // I8
+			6 => [p[0], p[0], p[0], p[1]], // IA88
+			8 => [255, 255, 255, p[0]], // A8
+			// pure blue is transparent
+			9 => [p[0], p[1], p[2], if p[..3] == [0, 0, 255] { 0 } else { 255 }],
+			10 => [p[2], p[1], p[0], if p[..3] == [255, 0, 0] { 0 } else { 255 }],
+			11 => [p[1], p[2], p[3], p[0]], // ARGB8888
+			12 => [p[2], p[1], p[0], p[3]], // BGRA8888
+			16 => [p[2], p[1], p[0], 255], // BGRX8888
+			22 => [p[0], p[1], 0, 255], // UV88
+			// hdr, linear so it gets clamped and gamma corrected
+			24 => {
+				let ch = |i: usize| (half_to_f32(u16::from_le_bytes([p[i * 2], p[i * 2 + 1]])).clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0) as u8;
+				[ch(0), ch(1), ch(2), (half_to_f32(u16::from_le_bytes([p[6], p[7]])).clamp(0.0, 1.0) * 255.0) as u8]
+			},
+			25 => [p[1], p[3], p[5], p[7]], // RGBA16161616, high bytes
+			_ => return None,
+		};
+		res.extend_from_slice(&pixel);
+	}
+	Some(res)
+}
+
+fn export_one(name: &str, bytes: &[u8], out_path: &str) {
+	println!("{name}");
+	let vtf: Vtf = match Vtf::parse(bytes) {
+		Ok(vtf) => vtf,
+		Err(e) => {
+			println!("\t{e}");
+			return;
+		},
+	};
+	print!("{}", vtf.info());
+	match vtf.top_mip_rgba() {
+		Ok((width, height, rgba)) => {
+			if let Some(dir) = Path::new(out_path).parent() { fs::create_dir_all(dir).unwrap(); }
+			write_png(out_path, width as u32, height as u32, &rgba);
+			println!("\texported to {out_path}");
+		},
+		Err(e) => println!("\tcouldn't export: {e}"),
+	}
+}
+
+// a single vtf file, the png goes next to it
+pub fn export_file(path: String) {
+	let bytes: Vec<u8> = fs::read(&path).unwrap_or_else(|e| {
+		println!("error while opening file: {e}");
+		std::process::exit(0);
+	});
+	export_one(&path, &bytes, &(path.trim_end_matches(".vtf").to_owned() + ".png"));
+}
+
+// every vtf in a map's pakfile, to file-vtf/ with the same paths
+pub fn export_pak(path: String, ld: &[VBSPLumpType]) {
+	let bsp: VBsp = VBsp::from_lumps(ld);
+	let pak: Pak = Pak::new(bsp.pakfile);
+	let out_dir: String = path.trim_end_matches(".bsp").to_owned() + "-vtf";
+	let mut count: usize = 0;
+	for entry in pak.entries.iter().filter(|e| e.name.to_lowercase().ends_with(".vtf")) {
+		count += 1;
+		if !is_safe_path(&entry.name) {
+			println!("{}\n\tnot exported, the path leaves the output directory", entry.name);
+			continue;
+		}
+		match pak.read(entry) {
+			Ok(bytes) => export_one(
+				&entry.name, &bytes,
+				&format!("{out_dir}/{}.png", &entry.name[..entry.name.len() - 4]),
+			),
+			Err(e) => println!("{}\n\t{e}", entry.name),
+		}
+	}
+	println!("{count} vtfs in the pakfile");
+}
@@ -0,0 +1,167 @@
+// wad2 (quake) and wad3 (goldsrc) texture archives.
+// textures with zeroed mip offsets aren't in the bsp, the engine loads them from one of the wads
+// listed in worldspawn's wad key. those are full paths from the mapper's machine
+// ("\half-life\valve\halflife.wad") so they're looked up relative to a game directory instead
+
+use std::{fs, path::{Path, PathBuf}};
+use crate::{
+	parse::{read_miptex_palette, read_mips},
+	tools::q1bsp::{Miptex, Q1Bsp},
+	utils::{entity_value, find_path_ignore_case},
+};
+
+// lump types we care about, everything else (palettes, fonts, status bar pics) is skipped
+const WAD2_MIPTEX: u8 = 0x44;
+const WAD3_MIPTEX: u8 = 0x43;
+
+#[allow(unused)]
+pub struct WadEntry {
+	pub name: String,
+	pub kind: u8,
+	pub compression: u8, // never used by any tool, 0 is uncompressed
+	pub offset: usize,
+	pub size: usize,
+}
+
+pub struct Wad {
+	pub path: PathBuf,
+	pub wad3: bool,
+	pub entries: Vec<WadEntry>,
+	bytes: Vec<u8>,
+}
+
+fn read_i32(bytes: &[u8], at: usize) -> Option<i32> {
+	Some(i32::from_le_bytes(bytes.get(at..at + 4)?.try_into().unwrap()))
+}
+
+fn read_name(bytes: &[u8]) -> String {
+	String::from_utf8_lossy(bytes.split(|b| *b == 0).next().unwrap()).to_string()
+}
+
+impl Wad {
+	pub fn read(path: &Path) -> Result<Wad, String> {
+		let bytes: Vec<u8> = fs::read(path).map_err(|e| e.to_string())?;
+		let wad3: bool = match bytes.get(0..4) {
+			Some(b"WAD2") => false,
+			Some(b"WAD3") => true,
+			_ => return Err("not a wad2 or wad3 file".to_string()),
+		};
+		let num_entries: usize = read_i32(&bytes, 4).unwrap_or(0).max(0) as usize;
+		let dir_offset: usize = read_i32(&bytes, 8).unwrap_or(0).max(0) as usize;
+
+		let mut entries: Vec<WadEntry> = vec![];
+		for i in 0..num_entries {
+			// filepos, disksize, size, type, compression, 2 bytes of padding, name[16]
+			let Some(entry) = bytes.get(dir_offset + i * 32..dir_offset + (i + 1) * 32) else {
+				return Err(format!("directory has {num_entries} entries but ends after {i}"));
+			};
+			entries.push(WadEntry {
+				name: read_name(&entry[16..32]),
+				kind: entry[12],
+				compression: entry[13],
+				offset: read_i32(entry, 0).unwrap().max(0) as usize,
+				size: read_i32(entry, 4).unwrap().max(0) as usize,
+			});
+		}
+
+		Ok(Wad { path: path.to_path_buf(), wad3, entries, bytes })
+	}
+
+	pub fn num_textures(&self) -> usize {
+		self.entries.iter().filter(|e| e.kind == WAD2_MIPTEX || e.kind == WAD3_MIPTEX).count()
+	}
+
+	// wad names are case insensitive (and some tools upper case them)
+	pub fn miptex(&self, name: &str) -> Option<Miptex> {
+		let entry: &WadEntry = self.entries.iter().find(|e| {
+			(e.kind == WAD2_MIPTEX || e.kind == WAD3_MIPTEX) && e.compression == 0 && e.name.eq_ignore_ascii_case(name)
+		})?;
+		// the lump is a miptex struct just like the ones in the bsp
+		let data: &[u8] = self.bytes.get(entry.offset..entry.offset + entry.size)?;
+		let width: u32 = read_i32(data, 16)? as u32;
+		let height: u32 = read_i32(data, 20)? as u32;
+		let offsets: [u32; 4] = [
+			read_i32(data, 24)? as u32, read_i32(data, 28)? as u32,
+			read_i32(data, 32)? as u32, read_i32(data, 36)? as u32,
+		];
+		let mips: Vec<Vec<u8>> = read_mips(data, width, height, &offsets);
+		if mips.is_empty() { return None; }
+		Some(Miptex {
+			name: name.to_string(),
+			width,
+			height,
+			offsets,
+			file_offset: entry.offset,
+			palette: if self.wad3 { read_miptex_palette(data, &offsets, &mips) } else { vec![] },
+			mips,
+		})
+	}
+}
+
+// tries every tail of the wad path under the game directory and its parent,
+// so "\half-life\valve\halflife.wad" is found with either half-life or half-life/valve as the game dir
+pub fn find_wad(game_dir: &Path, wad_path: &str) -> Option<PathBuf> {
+	let parts: Vec<&str> = wad_path.split(['/', '\\'])
+		.filter(|p| !p.is_empty() && !p.ends_with(':'))
+		.collect();
+	let bases: Vec<&Path> = [Some(game_dir), game_dir.parent()].into_iter().flatten().collect();
+	for start in 0..parts.len() {
+		for base in &bases {
+			let found: Option<PathBuf> = find_path_ignore_case(base, &parts[start..].join("/"));
+			if let Some(path) = found.filter(|p| p.is_file()) { return Some(path); }
+		}
+	}
+	None
+}
+
+pub enum TextureSource {
+	Embedded,
+	Wad(usize, Miptex), // index into TextureReport::wads
+	Missing,
+	NotSearched, // no game directory was given
+}
+
+pub struct TextureReport {
+	// every entry of the wad key, with the wad (or why it couldn't be loaded)
+	pub wads: Vec<(String, Result<Wad, String>)>,
+	pub sources: Vec<TextureSource>, // one per miptex
+}
+
+pub fn wad_list(bsp: &Q1Bsp) -> Vec<String> {
+	bsp.entities.iter()
+		.find(|e| entity_value(e, "classname") == Some("worldspawn"))
+		.and_then(|e| entity_value(e, "wad"))
+		.unwrap_or("")
+		.split(';')
+		.filter(|w| !w.trim().is_empty())
+		.map(|w| w.trim().to_string())
+		.collect()
+}
+
+// wads are searched in the order they're listed, like the engine does
+pub fn resolve(bsp: &Q1Bsp, game_dir: Option<&Path>) -> TextureReport {
+	let wads: Vec<(String, Result<Wad, String>)> = match game_dir {
+		Some(dir) => wad_list(bsp).into_iter()
+			.map(|w| {
+				let wad: Result<Wad, String> = match find_wad(dir, &w) {
+					Some(path) => Wad::read(&path).map_err(|e| format!("{}: {e}", path.display())),
+					None => Err("not found".to_string()),
+				};
+				(w, wad)
+			})
+			.collect(),
+		None => vec![],
+	};
+
+	let sources: Vec<TextureSource> = bsp.miptexs.iter()
+		.map(|m| {
+			if !m.mips.is_empty() { return TextureSource::Embedded; }
+			if game_dir.is_none() { return TextureSource::NotSearched; }
+			wads.iter().enumerate()
+				.find_map(|(i, (_, wad))| Some(TextureSource::Wad(i, wad.as_ref().ok()?.miptex(&m.name)?)))
+				.unwrap_or(TextureSource::Missing)
+		})
+		.collect();
+
+	TextureReport { wads, sources }
+}
@@ -1,4 +1,5 @@
-use core::fmt;
+use core::{fmt, ops};
+use std::{fs, path::{Path, PathBuf}};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Vector3 {
@@ -11,6 +12,63 @@ impl Vector3 {
 	pub fn new() -> Vector3 {
 		Vector3 { x: 0.0, y: 0.0, z: 0.0 }
 	}
+
+	pub fn from_array(arr: [f32; 3]) -> Vector3 {
+		Vector3 { x: arr[0], y: arr[1], z: arr[2] }
+	}
+
+	pub fn to_array(self) -> [f32; 3] {
+		[self.x, self.y, self.z]
+	}
+
+	pub fn dot(&self, other: &Vector3) -> f32 {
+		self.x * other.x + self.y * other.y + self.z * other.z
+	}
+
+	pub fn cross(&self, other: &Vector3) -> Vector3 {
+		Vector3 {
+			x: self.y * other.z - self.z * other.y,
+			y: self.z * other.x - self.x * other.z,
+			z: self.x * other.y - self.y * other.x,
+		}
+	}
+
+	pub fn length(&self) -> f32 {
+		self.dot(self).sqrt()
+	}
+
+	pub fn normalized(&self) -> Vector3 {
+		let len: f32 = self.length();
+		if len == 0.0 { *self } else { *self * (1.0 / len) }
+	}
+}
+
+impl ops::Add for Vector3 {
+	type Output = Vector3;
+	fn add(self, other: Vector3) -> Vector3 {
+		Vector3 { x: self.x + other.x, y: self.y + other.y, z: self.z + other.z }
+	}
+}
+
+impl ops::Sub for Vector3 {
+	type Output = Vector3;
+	fn sub(self, other: Vector3) -> Vector3 {
+		Vector3 { x: self.x - other.x, y: self.y - other.y, z: self.z - other.z }
+	}
+}
+
+impl ops::Mul<f32> for Vector3 {
+	type Output = Vector3;
+	fn mul(self, scale: f32) -> Vector3 {
+		Vector3 { x: self.x * scale, y: self.y * scale, z: self.z * scale }
+	}
+}
+
+impl ops::Neg for Vector3 {
+	type Output = Vector3;
+	fn neg(self) -> Vector3 {
+		Vector3 { x: -self.x, y: -self.y, z: -self.z }
+	}
 }
 
 impl fmt::Display for Vector3 {
@@ -27,51 +85,113 @@ pub fn bitflags_to_string<B: bitflags::Flags>(names: bitflags::iter::IterNames<B
         flag_str.push_str(name.0);
         flag_str.push_str(" | ");
     }
-    if flag_str == "" {
+    if flag_str.is_empty() {
         flag_str = "None".to_string();
     } else {
         flag_str = flag_str[..flag_str.len() - 3].to_string();
     }
 
-    return flag_str;
+    flag_str
 }
 
-// very very bad string parsing code
-// it works so its fine
+// entity lump is a list of { "key" "value" ... } blocks
+// values can contain spaces so we have to respect the quotes
 pub fn parse_entity_string(
 	ent_string: String,
 ) -> Vec<Vec<(String, String)>> {
 	let mut entities: Vec<Vec<(String, String)>> = vec![];
-	let mut clean_strings: Vec<String> = vec![];
+	let mut ent: Vec<(String, String)> = vec![];
+	let mut key: Option<String> = None;
 
-	for s in ent_string.split("}\n") {
-		clean_strings.push(s.replace("{", ""));
+	let mut chars = ent_string.chars();
+	while let Some(c) = chars.next() {
+		match c {
+			'{' => { ent = vec![]; key = None; },
+			'}' => entities.push(std::mem::take(&mut ent)),
+			'"' => {
+				let s: String = chars.by_ref().take_while(|c| *c != '"').collect();
+				match key.take() {
+					Some(k) => ent.push((k, s)),
+					None => key = Some(s),
+				}
+			},
+			_ => {},
+		}
 	}
 
-	let mut split_attrs: Vec<Vec<String>> = vec![];
-	for s in clean_strings {
-		split_attrs.push(
-			s.split("\n")
-			.map(|s| { s.to_owned() })
-			.collect()
-		);
-	}
+	entities
+}
+
+// looks up a key in an entity from the entity lump
+pub fn entity_value<'a>(ent: &'a [(String, String)], key: &str) -> Option<&'a str> {
+	ent.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
 
-	for string in split_attrs {
-		if string == vec![""] { continue; }
-		let mut ent: Vec<(String, String)> = vec![];
-		for attrs in string {
-			if attrs == "" || attrs == "\0" { continue; }
-			let splitted: Vec<String> = attrs.split(" ")
-			.map(|s| { s.trim_matches('\"').to_owned() })
-			.collect();
+// "x y z" strings like the ones in origin keys
+pub fn parse_vector(s: &str) -> Option<Vector3> {
+	let parts: Vec<f32> = s.split_whitespace()
+		.filter_map(|p| p.parse::<f32>().ok())
+		.collect();
+	if parts.len() != 3 { return None; }
+	Some(Vector3 { x: parts[0], y: parts[1], z: parts[2] })
+}
 
-			ent.push((splitted[0].clone(), splitted[1].clone()));
+// standard alphabet with padding, used for data uris
+pub fn base64_encode(data: &[u8]) -> String {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut res: String = String::with_capacity(data.len().div_ceil(3) * 4);
+	for chunk in data.chunks(3) {
+		let n: u32 = (chunk[0] as u32) << 16
+			| (*chunk.get(1).unwrap_or(&0) as u32) << 8
+			| *chunk.get(2).unwrap_or(&0) as u32;
+		for i in 0..4 {
+			if i <= chunk.len() {
+				res.push(ALPHABET[(n >> (18 - i * 6)) as usize & 63] as char);
+			} else {
+				res.push('=');
+			}
 		}
-		entities.push(ent);
 	}
+	res
+}
 
-	entities
+// a quoted json string, entity values can have quotes and backslashes in them
+pub fn json_string(s: &str) -> String {
+	let mut res: String = String::with_capacity(s.len() + 2);
+	res.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => res.push_str("\\\""),
+			'\\' => res.push_str("\\\\"),
+			'\n' => res.push_str("\\n"),
+			c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+			c => res.push(c),
+		}
+	}
+	res.push('"');
+	res
+}
+
+// paths that come from a map can't be allowed to point outside of the directory they're joined to
+pub fn is_safe_path(name: &str) -> bool {
+	let absolute: bool = name.starts_with(['/', '\\']) || name.chars().nth(1) == Some(':');
+	!absolute && !name.split(['/', '\\']).any(|part| part == ".." || part == ".")
+}
+
+// a file under base, ignoring the case of every path component since
+// paths in maps usually come from windows ("Materials\Brick\Wall01.vmt")
+pub fn find_path_ignore_case(base: &Path, path: &str) -> Option<PathBuf> {
+	if !is_safe_path(path) { return None; }
+	path.split(['/', '\\'])
+		.filter(|p| !p.is_empty())
+		.try_fold(base.to_path_buf(), |dir, part| {
+			let exact: PathBuf = dir.join(part);
+			if exact.exists() { return Some(exact); }
+			fs::read_dir(&dir).ok()?
+				.filter_map(|e| e.ok())
+				.find(|e| e.file_name().to_string_lossy().eq_ignore_ascii_case(part))
+				.map(|e| e.path())
+		})
 }
 
 pub fn int_to_gsrc_planetype(val: &i32) -> &str {